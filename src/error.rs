@@ -1,5 +1,7 @@
 use crate::status::ftp_status;
 use std::fmt;
+use std::io;
+use std::net::SocketAddr;
 
 /// Enum containing all FTP errors the library uses
 #[derive(Debug, Clone)]
@@ -38,6 +40,18 @@ pub enum FtpError {
     ActionAborted,
     /// Insufficient storage space
     InsufficientStorage,
+    /// The host name could not be resolved to any address
+    HostResolutionError(String),
+    /// Every address the host resolved to failed to connect
+    ConnectionAttemptsFailed(Vec<(SocketAddr, FtpError)>),
+    /// Error setting up or using TLS
+    TlsError(String),
+    /// The server did not respond before the timeout expired
+    Timeout,
+    /// Error reading or writing a file on the local machine
+    LocalFileError(String),
+    /// A line of a directory listing could not be parsed (line number, reason)
+    ListingParseError(usize, String),
 }
 
 impl fmt::Display for FtpError {
@@ -73,6 +87,28 @@ impl FtpError {
             FtpError::ServiceNotReady => "Service not ready".to_string(),
             FtpError::ActionAborted => "Action aborted by FTP server".to_string(),
             FtpError::InsufficientStorage => "Insufficient storage on server".to_string(),
+            FtpError::HostResolutionError(v) => format!("Unable to resolve host: {:?}", v),
+            FtpError::ConnectionAttemptsFailed(v) => {
+                let failures: Vec<String> = v
+                    .iter()
+                    .map(|(addr, err)| format!("{} ({})", addr, err))
+                    .collect();
+                format!("Unable to connect to any address: {}", failures.join(", "))
+            }
+            FtpError::TlsError(v) => format!("TLS error: {}", v),
+            FtpError::Timeout => "Timed out waiting for the FTP server".to_string(),
+            FtpError::LocalFileError(v) => format!("Error accessing local file: {}", v),
+            FtpError::ListingParseError(line, v) => {
+                format!("Invalid directory listing on line {}: {}", line, v)
+            }
+        }
+    }
+
+    /// Converts an I/O error into `Timeout` if a timeout expired, or the given error otherwise.
+    pub(crate) fn from_io_error(err: &io::Error, fallback: FtpError) -> FtpError {
+        match err.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => FtpError::Timeout,
+            _ => fallback,
         }
     }
 
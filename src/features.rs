@@ -0,0 +1,128 @@
+/// The extensions advertised by an FTP server in its reply to `FEAT` (RFC 2389).
+///
+/// Feature names are matched without regard to case. Some features carry
+/// parameters after their name, such as the facts of `MLST` or the mechanisms
+/// of `AUTH`, which are available through the helper methods.
+///
+/// ## Example:
+/// ```rust
+/// use ftp_lib::FtpResponse;
+///
+/// let response = FtpResponse {
+///     status: 211,
+///     content: "211-Features:\r\n MLST type*;size*;modify*;perm;\r\n REST STREAM\r\n UTF8\r\n211 End\r\n"
+///         .to_string(),
+/// };
+///
+/// let features = response.parse_features().unwrap();
+/// assert!(features.supports("mlst"));
+/// assert!(features.rest_stream());
+/// assert_eq!(features.mlst_facts(), vec!["type", "size", "modify", "perm"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Features {
+	/// Each advertised feature as its uppercased name and its parameters
+	features: Vec<(String, String)>,
+}
+
+impl Features {
+	/// Builds the features from the lines of a `FEAT` reply.
+	///
+	/// Features are the lines starting with a space, any other line is part of
+	/// the reply's framing and is ignored.
+	pub(crate) fn from_lines(lines: &[&str]) -> Features {
+		let features = lines
+			.iter()
+			.filter(|line| line.starts_with(' '))
+			.map(|line| {
+				let line = line.trim();
+				match line.find(' ') {
+					Some(split) => (
+						line[..split].to_ascii_uppercase(),
+						line[split + 1..].trim().to_string(),
+					),
+					None => (line.to_ascii_uppercase(), String::new()),
+				}
+			})
+			.filter(|(name, _)| !name.is_empty())
+			.collect();
+
+		Features { features }
+	}
+
+	/// Checks whether the server advertised the given feature, such as `MLST` or `EPSV`.
+	pub fn supports(&self, feature: &str) -> bool {
+		self.features
+			.iter()
+			.any(|(name, _)| name.eq_ignore_ascii_case(feature))
+	}
+
+	/// Gets the parameters advertised for the given feature.
+	///
+	/// An empty string is returned for features without parameters, and
+	/// `None` for features that were not advertised. When a feature is listed
+	/// more than once, the parameters of the first line are returned.
+	pub fn parameters(&self, feature: &str) -> Option<&str> {
+		self.features
+			.iter()
+			.find(|(name, _)| name.eq_ignore_ascii_case(feature))
+			.map(|(_, parameters)| parameters.as_str())
+	}
+
+	/// Gets the names of every advertised feature, uppercased.
+	pub fn names(&self) -> Vec<&str> {
+		self.features.iter().map(|(name, _)| name.as_str()).collect()
+	}
+
+	/// Gets the facts the server can give in `MLST` and `MLSD` listings (RFC 3659).
+	///
+	/// The names are lowercased, and the `*` marking the facts the server
+	/// sends by default is removed.
+	pub fn mlst_facts(&self) -> Vec<String> {
+		self.mlst_fact_list()
+			.map(|fact| fact.trim_end_matches('*').to_ascii_lowercase())
+			.collect()
+	}
+
+	/// Gets the facts the server sends in `MLST` and `MLSD` listings by default.
+	pub fn mlst_default_facts(&self) -> Vec<String> {
+		self.mlst_fact_list()
+			.filter(|fact| fact.ends_with('*'))
+			.map(|fact| fact.trim_end_matches('*').to_ascii_lowercase())
+			.collect()
+	}
+
+	/// Checks whether `REST STREAM` is advertised, allowing transfers to be restarted (RFC 3659).
+	pub fn rest_stream(&self) -> bool {
+		match self.parameters("REST") {
+			Some(parameters) => parameters.eq_ignore_ascii_case("STREAM"),
+			None => false,
+		}
+	}
+
+	/// Checks whether the server uses UTF-8 for path names (RFC 2640).
+	pub fn utf8(&self) -> bool {
+		self.supports("UTF8")
+	}
+
+	/// Gets the security mechanisms accepted by `AUTH` (RFC 2228), such as `TLS`.
+	///
+	/// Servers list these either on a single line separated by `;` or on
+	/// several `AUTH` lines, both of which are handled.
+	pub fn auth_mechanisms(&self) -> Vec<String> {
+		self.features
+			.iter()
+			.filter(|(name, _)| name == "AUTH")
+			.flat_map(|(_, parameters)| parameters.split([';', ' ']))
+			.filter(|mechanism| !mechanism.is_empty())
+			.map(|mechanism| mechanism.to_ascii_uppercase())
+			.collect()
+	}
+
+	fn mlst_fact_list(&self) -> impl Iterator<Item = &str> {
+		self.parameters("MLST")
+			.unwrap_or("")
+			.split(';')
+			.filter(|fact| !fact.is_empty())
+	}
+}
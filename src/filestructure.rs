@@ -1,50 +1,554 @@
 use regex::Regex;
 
 use std::str::FromStr;
+use std::time::Duration;
+use std::time::SystemTime;
 
-#[derive(Debug)]
+use crate::error::FtpError;
+use crate::time;
+
+/// The kind of an entry in a `LIST` listing.
+#[derive(Debug, Clone, PartialEq)]
 pub enum DirectoryItemType {
 	Link,
 	File,
 	Directory,
 }
 
-#[derive(Debug)]
+/// An entry of a `LIST` listing.
+///
+/// The listing format is not standardised, so the details which could not
+/// be found in a line are left as `None`. Parsing a `DirectoryItem` from a
+/// string reads the Unix `ls -l` format, while other formats are handled by
+/// the parsers in `listparser`. Lines which only loosely follow `ls -l`
+/// still give their kind, date and name.
+///
+/// ## Example:
+/// ```rust
+/// use ftp_lib::filestructure::{DirectoryItem, DirectoryItemType};
+///
+/// let item: DirectoryItem = "lrwxrwxrwx   1 ftp      ftp            11 Mar  3  2019 latest -> release-1.2"
+///     .parse()
+///     .unwrap();
+/// assert_eq!(item.name(), "latest");
+/// assert_eq!(item.item_type(), &DirectoryItemType::Link);
+/// assert_eq!(item.link_target(), Some("release-1.2"));
+/// assert_eq!(item.permissions(), Some(0o777));
+/// assert_eq!(item.owner(), Some("ftp"));
+/// assert_eq!(item.size(), Some(11));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
 pub struct DirectoryItem {
 	name: String,
 	item_type: DirectoryItemType,
+	size: Option<u64>,
+	permissions: Option<u32>,
+	link_count: Option<u64>,
+	owner: Option<String>,
+	group: Option<String>,
+	modified: Option<SystemTime>,
+	link_target: Option<String>,
+}
+
+impl DirectoryItem {
+	/// Creates an entry with the given name and kind, for use by `ListParser` implementations.
+	///
+	/// The other details start out unknown and are filled in with the `with_` methods.
+	///
+	/// ## Example:
+	/// ```rust
+	/// use ftp_lib::filestructure::{DirectoryItem, DirectoryItemType};
+	///
+	/// let item = DirectoryItem::new("notes.txt", DirectoryItemType::File)
+	///     .with_size(42)
+	///     .with_owner("ftp");
+	/// assert_eq!(item.size(), Some(42));
+	/// assert_eq!(item.group(), None);
+	/// ```
+	pub fn new(name: &str, item_type: DirectoryItemType) -> DirectoryItem {
+		DirectoryItem {
+			name: name.to_string(),
+			item_type,
+			size: None,
+			permissions: None,
+			link_count: None,
+			owner: None,
+			group: None,
+			modified: None,
+			link_target: None,
+		}
+	}
+
+	/// Sets the size of the entry in bytes.
+	pub fn with_size(mut self, size: u64) -> DirectoryItem {
+		self.size = Some(size);
+		self
+	}
+
+	/// Sets the Unix permission bits of the entry.
+	pub fn with_permissions(mut self, permissions: u32) -> DirectoryItem {
+		self.permissions = Some(permissions);
+		self
+	}
+
+	/// Sets the number of hard links to the entry.
+	pub fn with_link_count(mut self, link_count: u64) -> DirectoryItem {
+		self.link_count = Some(link_count);
+		self
+	}
+
+	/// Sets the name of the user owning the entry.
+	pub fn with_owner(mut self, owner: &str) -> DirectoryItem {
+		self.owner = Some(owner.to_string());
+		self
+	}
+
+	/// Sets the name of the group owning the entry.
+	pub fn with_group(mut self, group: &str) -> DirectoryItem {
+		self.group = Some(group.to_string());
+		self
+	}
+
+	/// Sets the time the entry was last modified.
+	pub fn with_modified(mut self, modified: SystemTime) -> DirectoryItem {
+		self.modified = Some(modified);
+		self
+	}
+
+	/// Sets the path a symbolic link points to.
+	pub fn with_link_target(mut self, link_target: &str) -> DirectoryItem {
+		self.link_target = Some(link_target.to_string());
+		self
+	}
+
+	/// Gets the name of the entry, without the target of a link.
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// Gets the kind of the entry.
+	pub fn item_type(&self) -> &DirectoryItemType {
+		&self.item_type
+	}
+
+	/// Gets the size of the entry in bytes.
+	pub fn size(&self) -> Option<u64> {
+		self.size
+	}
+
+	/// Gets the Unix permission bits of the entry, including the setuid, setgid and sticky bits.
+	pub fn permissions(&self) -> Option<u32> {
+		self.permissions
+	}
+
+	/// Gets the number of hard links to the entry.
+	pub fn link_count(&self) -> Option<u64> {
+		self.link_count
+	}
+
+	/// Gets the name of the user owning the entry.
+	pub fn owner(&self) -> Option<&str> {
+		self.owner.as_deref()
+	}
+
+	/// Gets the name of the group owning the entry.
+	pub fn group(&self) -> Option<&str> {
+		self.group.as_deref()
+	}
+
+	/// Gets the time the entry was last modified.
+	///
+	/// Listings only give the year for older entries, so for recent entries
+	/// the year is guessed as the last one where the date is not in the
+	/// future. Servers rarely say which time zone they use, so the time is
+	/// read as UTC.
+	pub fn modified(&self) -> Option<SystemTime> {
+		self.modified
+	}
+
+	/// Gets the path a symbolic link points to.
+	pub fn link_target(&self) -> Option<&str> {
+		self.link_target.as_deref()
+	}
 }
 
 impl FromStr for DirectoryItem {
 	type Err = String;
 
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		lazy_static! {
+			static ref RE: Regex = Regex::new(concat!(
+				// Type, permissions and an optional ACL marker
+				r"^([a-z-])([rwxsStT-]{9})[+@.]?\s+",
+				// Link count, owner and the group some servers leave out
+				r"(\d+)\s+(\S+)\s+(?:(\S+)\s+)?",
+				// Size, modification date and the name
+				r"(\d+)\s+([A-Za-z]{3})\s+(\d{1,2})\s+(\d{4}|\d{1,2}:\d{2})\s+(.+)$",
+			))
+			.unwrap();
+			// Any other line with a date followed by the name, leaving the other details unknown
+			static ref DATE_AND_NAME_RE: Regex =
+				Regex::new(r"([A-Za-z]{3})\s+(\d{1,2})\s+(\d{4}|\d{1,2}:\d{2})\s+(.+)$").unwrap();
+		}
+
+		let s = s.trim_end_matches(['\r', '\n']);
+
 		// Process the first character for the file type
-		let item_type = match &s[0..1] {
-			"d" => DirectoryItemType::Directory,
-			"-" => DirectoryItemType::File,
-			"l" => DirectoryItemType::Link,
+		let item_type = match s.get(0..1) {
+			Some("d") => DirectoryItemType::Directory,
+			Some("-") => DirectoryItemType::File,
+			Some("l") => DirectoryItemType::Link,
 			_ => return Err("Unknown type".to_string()),
 		};
 
-		lazy_static! {
-			static ref RE: Regex = Regex::new(
-				r"[A-z]{3}[ ]{1,}[0-9]{1,2}[ ]{1,}([0-9]{4}|[0-9]{2}:[0-9]{2})[ ]{1,}(.{1,})",
-			).unwrap();
+		let mut item = match RE.captures(s) {
+			Some(captures) => DirectoryItem {
+				name: captures[10].to_string(),
+				item_type,
+				size: captures[6].parse().ok(),
+				permissions: Some(parse_permissions(&captures[2])),
+				link_count: captures[3].parse().ok(),
+				owner: Some(captures[4].to_string()),
+				group: captures.get(5).map(|group| group.as_str().to_string()),
+				modified: Some(parse_list_time(&captures[7], &captures[8], &captures[9])?),
+				link_target: None,
+			},
+			None => match DATE_AND_NAME_RE.captures(s) {
+				Some(captures) => DirectoryItem::new(&captures[4], item_type)
+					.with_modified(parse_list_time(&captures[1], &captures[2], &captures[3])?),
+				None => return Err(format!("Unrecognised entry {:?}", s)),
+			},
+		};
+
+		// Links are listed as `name -> target`
+		if item.item_type == DirectoryItemType::Link {
+			if let Some(split) = item.name.find(" -> ") {
+				item.link_target = Some(item.name[split + 4..].to_string());
+				item.name.truncate(split);
+			}
+		}
+
+		Ok(item)
+	}
+}
+
+/// Converts the 9 permission characters of `ls -l` (such as `rwsr-xr-t`) to mode bits.
+fn parse_permissions(permissions: &str) -> u32 {
+	let mut mode = 0;
+	for (index, c) in permissions.chars().enumerate() {
+		// Bits are listed from the most significant: rwx for the owner, group and others
+		let bit = 1 << (8 - index);
+		match c {
+			'r' | 'w' | 'x' => mode |= bit,
+			// The execute position also holds the setuid, setgid and sticky bits
+			's' | 't' => mode |= bit | (0o4000 >> (index / 3)),
+			'S' | 'T' => mode |= 0o4000 >> (index / 3),
+			_ => (),
+		}
+	}
+	mode
+}
+
+/// Converts the `Mon DD HH:MM` or `Mon DD YYYY` date of `ls -l` to a time.
+fn parse_list_time(month: &str, day: &str, year_or_time: &str) -> Result<SystemTime, String> {
+	let parse = || {
+		let month = time::parse_month(month)?;
+		let day = day.parse().ok()?;
+
+		match year_or_time.find(':') {
+			Some(split) => {
+				let hour = year_or_time[..split].parse().ok()?;
+				let minute = year_or_time[split + 1..].parse().ok()?;
+
+				// Recent entries leave out the year, which is the latest one not in the future.
+				// A day of slack allows for servers ahead of UTC
+				let now = SystemTime::now();
+				let latest = now + Duration::from_secs(86_400);
+				let year = time::year_of(now);
+				[year, year - 1]
+					.iter()
+					.filter_map(|&year| time::from_civil(year, month, day, hour, minute, 0, 0))
+					.find(|&modified| modified <= latest)
+			}
+			None => time::from_civil(year_or_time.parse().ok()?, month, day, 0, 0, 0, 0),
+		}
+	};
+
+	parse().ok_or_else(|| format!("Invalid date \"{} {} {}\"", month, day, year_or_time))
+}
+
+/// The kind of an entry in an `MLSD` or `MLST` listing, from its `type` fact.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MlsEntryType {
+	/// A regular file (`file`)
+	File,
+	/// A directory (`dir`)
+	Dir,
+	/// The directory being listed (`cdir`)
+	CurrentDir,
+	/// The parent of the directory being listed (`pdir`)
+	ParentDir,
+	/// Any other type, such as `OS.unix=slink`, as it was sent
+	Other(String),
+}
+
+/// An entry of an `MLSD` or `MLST` listing (RFC 3659).
+///
+/// The standard facts are parsed into their own fields, while every fact the
+/// server sent, standard or not, can be looked up with `fact`.
+///
+/// ## Example:
+/// ```rust
+/// use ftp_lib::filestructure::{MlsEntry, MlsEntryType};
+///
+/// let entry: MlsEntry = "type=file;size=1024;UNIX.mode=0644; my file; v2.txt".parse().unwrap();
+/// assert_eq!(entry.name(), "my file; v2.txt");
+/// assert_eq!(entry.entry_type(), Some(&MlsEntryType::File));
+/// assert_eq!(entry.size(), Some(1024));
+/// assert_eq!(entry.unix_mode(), Some(0o644));
+/// assert_eq!(entry.fact("unix.mode"), Some("0644"));
+///
+/// // Facts must be well formed
+/// assert!("type=file;size=big; notes.txt".parse::<MlsEntry>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlsEntry {
+	name: String,
+	facts: Vec<(String, String)>,
+	entry_type: Option<MlsEntryType>,
+	size: Option<u64>,
+	modified: Option<SystemTime>,
+	created: Option<SystemTime>,
+	unix_mode: Option<u32>,
+}
+
+impl MlsEntry {
+	/// Gets the name of the entry, exactly as it was sent.
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// Gets the value of the given fact, whose name is matched without regard to case.
+	pub fn fact(&self, name: &str) -> Option<&str> {
+		self.facts
+			.iter()
+			.find(|(fact, _)| fact.eq_ignore_ascii_case(name))
+			.map(|(_, value)| value.as_str())
+	}
+
+	/// Gets every fact sent for the entry, with their names lowercased.
+	pub fn facts(&self) -> &[(String, String)] {
+		&self.facts
+	}
+
+	/// Gets the kind of the entry (`type` fact).
+	pub fn entry_type(&self) -> Option<&MlsEntryType> {
+		self.entry_type.as_ref()
+	}
+
+	/// Gets the size of the file in bytes (`size` fact).
+	pub fn size(&self) -> Option<u64> {
+		self.size
+	}
+
+	/// Gets the time the entry was last modified (`modify` fact).
+	pub fn modified(&self) -> Option<SystemTime> {
+		self.modified
+	}
+
+	/// Gets the time the entry was created (`create` fact).
+	pub fn created(&self) -> Option<SystemTime> {
+		self.created
+	}
+
+	/// Gets the permissions the user has on the entry, such as `adfrw` (`perm` fact).
+	pub fn perm(&self) -> Option<&str> {
+		self.fact("perm")
+	}
+
+	/// Gets the identifier which is the same for every name of the same file (`unique` fact).
+	pub fn unique(&self) -> Option<&str> {
+		self.fact("unique")
+	}
+
+	/// Gets the Unix permission bits of the entry (`unix.mode` fact).
+	pub fn unix_mode(&self) -> Option<u32> {
+		self.unix_mode
+	}
+}
+
+impl FromStr for MlsEntry {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim_end_matches(['\r', '\n']);
+
+		// The facts end at the first space, everything after it is the name
+		let (facts_raw, name) = match s.find(' ') {
+			Some(split) => (&s[..split], &s[split + 1..]),
+			None => return Err("Missing space before the name".to_string()),
+		};
+		if name.is_empty() {
+			return Err("Missing name".to_string());
+		}
+		if !facts_raw.is_empty() && !facts_raw.ends_with(';') {
+			return Err(format!("Facts do not end with \";\": {:?}", facts_raw));
 		}
 
-		let name_captures = match RE.captures(&s) {
-			Some(v) => v,
-			None => return Err("No name found".to_string()),
+		let mut entry = MlsEntry {
+			name: name.to_string(),
+			facts: vec![],
+			entry_type: None,
+			size: None,
+			modified: None,
+			created: None,
+			unix_mode: None,
 		};
 
-		if name_captures.len() < 3 {
-			return Err("No name found".to_string());
+		for fact in facts_raw.split(';').filter(|fact| !fact.is_empty()) {
+			let (fact_name, value) = match fact.find('=') {
+				Some(split) if split > 0 => (fact[..split].to_ascii_lowercase(), &fact[split + 1..]),
+				_ => return Err(format!("Invalid fact {:?}", fact)),
+			};
+
+			match fact_name.as_str() {
+				"type" => {
+					entry.entry_type = Some(match value.to_ascii_lowercase().as_str() {
+						"file" => MlsEntryType::File,
+						"dir" => MlsEntryType::Dir,
+						"cdir" => MlsEntryType::CurrentDir,
+						"pdir" => MlsEntryType::ParentDir,
+						_ => MlsEntryType::Other(value.to_string()),
+					})
+				}
+				"size" => match value.parse() {
+					Ok(v) => entry.size = Some(v),
+					Err(_) => return Err(format!("Invalid size {:?}", value)),
+				},
+				"modify" => match time::parse_time_val(value) {
+					Some(v) => entry.modified = Some(v),
+					None => return Err(format!("Invalid modify time {:?}", value)),
+				},
+				"create" => match time::parse_time_val(value) {
+					Some(v) => entry.created = Some(v),
+					None => return Err(format!("Invalid create time {:?}", value)),
+				},
+				"unix.mode" => match u32::from_str_radix(value, 8) {
+					Ok(v) => entry.unix_mode = Some(v),
+					Err(_) => return Err(format!("Invalid unix.mode {:?}", value)),
+				},
+				_ => (),
+			}
+
+			entry.facts.push((fact_name, value.to_string()));
+		}
+
+		Ok(entry)
+	}
+}
+
+/// Parses the lines of an `MLSD` listing, reporting the first line which is not a valid entry.
+pub(crate) fn parse_mlsd(listing: &str) -> Result<Vec<MlsEntry>, FtpError> {
+	let mut entries = vec![];
+	for (index, line) in listing.split('\n').enumerate() {
+		let line = line.trim_end_matches('\r');
+		if line.is_empty() {
+			continue;
+		}
+
+		match line.parse() {
+			Ok(entry) => entries.push(entry),
+			Err(e) => return Err(FtpError::ListingParseError(index + 1, e)),
+		}
+	}
+	Ok(entries)
+}
+
+/// Details about a single file or directory, returned by `FtpConnection::stat`.
+///
+/// Depending on what the server supports, the details come from `MLST`,
+/// from `SIZE` and `MDTM`, or from a `LIST` of the path, so the ones the
+/// server could not give are left as `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+	item_type: Option<DirectoryItemType>,
+	size: Option<u64>,
+	modified: Option<SystemTime>,
+	permissions: Option<u32>,
+}
+
+impl Metadata {
+	pub(crate) fn new(
+		item_type: Option<DirectoryItemType>,
+		size: Option<u64>,
+		modified: Option<SystemTime>,
+	) -> Metadata {
+		Metadata {
+			item_type,
+			size,
+			modified,
+			permissions: None,
 		}
+	}
+
+	/// Gets the kind of the entry.
+	pub fn item_type(&self) -> Option<&DirectoryItemType> {
+		self.item_type.as_ref()
+	}
 
-		Ok(DirectoryItem {
-			name: name_captures[2].to_string(),
+	/// Checks whether the entry is a directory.
+	pub fn is_dir(&self) -> bool {
+		self.item_type == Some(DirectoryItemType::Directory)
+	}
+
+	/// Gets the size of the entry in bytes.
+	pub fn size(&self) -> Option<u64> {
+		self.size
+	}
+
+	/// Gets the time the entry was last modified.
+	pub fn modified(&self) -> Option<SystemTime> {
+		self.modified
+	}
+
+	/// Gets the Unix permission bits of the entry.
+	pub fn permissions(&self) -> Option<u32> {
+		self.permissions
+	}
+}
+
+impl From<MlsEntry> for Metadata {
+	fn from(entry: MlsEntry) -> Metadata {
+		let item_type = match entry.entry_type {
+			Some(MlsEntryType::File) => Some(DirectoryItemType::File),
+			Some(MlsEntryType::Dir) | Some(MlsEntryType::CurrentDir) | Some(MlsEntryType::ParentDir) => {
+				Some(DirectoryItemType::Directory)
+			}
+			// Servers may add the link target as `OS.unix=slink:target`
+			Some(MlsEntryType::Other(ref other))
+				if other.to_ascii_lowercase().starts_with("os.unix=slink") =>
+			{
+				Some(DirectoryItemType::Link)
+			}
+			_ => None,
+		};
+
+		Metadata {
 			item_type,
-		})
+			size: entry.size,
+			modified: entry.modified,
+			permissions: entry.unix_mode,
+		}
+	}
+}
+
+impl From<DirectoryItem> for Metadata {
+	fn from(item: DirectoryItem) -> Metadata {
+		Metadata {
+			item_type: Some(item.item_type),
+			size: item.size,
+			modified: item.modified,
+			permissions: item.permissions,
+		}
 	}
 }
@@ -7,7 +7,7 @@
 //! use std::net::SocketAddrV4;
 //!
 //! let mut ftp_conn = FtpConnection::connect(
-//!     "127.0.0.1:21".parse().unwrap()
+//!     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 //! ).unwrap();
 //!
 //! ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
@@ -33,16 +33,31 @@
 extern crate regex;
 
 use std::net::Ipv4Addr;
+use std::net::SocketAddr;
 use std::net::SocketAddrV4;
+use std::net::TcpListener;
 use std::net::TcpStream;
+use std::net::ToSocketAddrs;
+
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
 
 use std::str::FromStr;
 
 use std::io::BufRead;
 use std::io::BufReader;
 use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
 use std::io::Write;
 
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::path::Path;
+
+use socket2::SockRef;
+
 /// Module containing all errors returned by ftp_lib.
 pub mod error;
 use error::FtpError;
@@ -55,16 +70,92 @@ use status::ftp_status;
 /// Module for handling the outputs of ftp_conn.list()
 pub mod filestructure;
 
+/// Module containing the parsers for the different `LIST` formats.
+pub mod listparser;
+use listparser::ListParser;
+
+/// Module containing the streaming types used for file transfers.
+pub mod transfer;
+
+/// Module containing the extensions a server can advertise with `FEAT`.
+pub mod features;
+use features::Features;
+
+/// Module containing the stream type shared by control and data connections.
+mod stream;
+
+/// Module containing the date and time conversions used by listings and `MDTM`.
+mod time;
+use stream::FtpStream;
+
+/// Module containing the settings for FTPS connections (requires the `tls` feature).
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(feature = "tls")]
+pub use rustls;
+use transfer::FtpReader;
+use transfer::FtpWriter;
+
 /// Data structure that contains a response from the FTP server.
 #[derive(Debug, Clone)]
 pub struct FtpResponse {
 	/// The status code recieved (see status.rs for more information)
 	pub status: u32,
 	/// The content of the message recieved (FTP often has very human readable responses)
+	///
+	/// For replies spanning multiple lines, every line is included as it was
+	/// received, see `lines` to go through them one at a time.
 	pub content: String,
 }
 
 impl FtpResponse {
+	/// Gets every line of the message, without the line endings.
+	///
+	/// ## Example:
+	/// ```rust
+	/// use ftp_lib::FtpResponse;
+	///
+	/// let response = FtpResponse {
+	///     status: 211,
+	///     content: "211-Features:\r\n UTF8\r\n211 End\r\n".to_string(),
+	/// };
+	/// assert_eq!(response.lines(), vec!["211-Features:", " UTF8", "211 End"]);
+	/// ```
+	pub fn lines(&self) -> Vec<&str> {
+		self.content.lines().collect()
+	}
+
+	/// Gets the text of the message without the status codes.
+	///
+	/// Every line of the reply is included, joined with `\n`. The status code
+	/// is removed from the lines that start with it, while the other lines of
+	/// a multi-line reply are kept as they were sent.
+	///
+	/// ## Example:
+	/// ```rust
+	/// use ftp_lib::FtpResponse;
+	///
+	/// let response = FtpResponse {
+	///     status: 211,
+	///     content: "211-Features:\r\n UTF8\r\n211 End\r\n".to_string(),
+	/// };
+	/// assert_eq!(response.message(), "Features:\n UTF8\nEnd");
+	/// ```
+	pub fn message(&self) -> String {
+		let status_code = self.status.to_string();
+
+		let lines: Vec<&str> = self
+			.lines()
+			.into_iter()
+			.map(|line| match line.strip_prefix(status_code.as_str()) {
+				Some(rest) if rest.starts_with(' ') || rest.starts_with('-') => &rest[1..],
+				Some(rest) if rest.is_empty() => rest,
+				_ => line,
+			})
+			.collect();
+		lines.join("\n")
+	}
+
 	/// Parses the IP and port information from a `PASV` response.
 	///
 	/// The `PASV` response contains a section of data enclosed inside of parentheses.
@@ -75,10 +166,7 @@ impl FtpResponse {
 	/// ```rust
 	/// use ftp_lib::FtpResponse;
 	///
-	/// let response = FtpResponse {
-	///     status: 227,
-	///     content: "227 Entering passive (127,0,0,1,250,29)".to_string()
-	/// };
+	/// let response: FtpResponse = "227 Entering passive (127,0,0,1,250,29)".parse().unwrap();
 	/// println!("{:?}", response.parse_pasv_addr().unwrap());
 	/// ```
 	pub fn parse_pasv_addr(&self) -> Result<SocketAddrV4, FtpError> {
@@ -122,6 +210,145 @@ impl FtpResponse {
 		);
 		Ok(datastream_addr)
 	}
+
+	/// Parses the port information from an `EPSV` response.
+	///
+	/// The `EPSV` response (RFC 2428) contains the port enclosed inside of parentheses,
+	/// surrounded by delimiters in the following format: (|||port|).
+	/// Only the port is given, the host is the one the control connection uses.
+	///
+	/// ## Example:
+	/// ```rust
+	/// use ftp_lib::FtpResponse;
+	///
+	/// let response: FtpResponse = "229 Entering Extended Passive Mode (|||6446|)".parse().unwrap();
+	/// assert_eq!(response.parse_epsv_port().unwrap(), 6446);
+	/// ```
+	pub fn parse_epsv_port(&self) -> Result<u16, FtpError> {
+		// Make sure the type being converted really is an EPSV response
+		if self.status != ftp_status::ENTERING_EXTENDED_PASSIVE {
+			return Err(InvalidTypeError);
+		}
+
+		// Fetch the area within the parentheses of the EPSV response
+		let epsv_raw = self.content.as_str();
+		let epsv_section = match (epsv_raw.find('('), epsv_raw.rfind(')')) {
+			(Some(start), Some(end)) if start < end => &epsv_raw[start + 1..end],
+			_ => return Err(InvalidResponseError(self.clone())),
+		};
+
+		// The delimiter is the first character and must be repeated 4 times: <d><d><d>port<d>
+		let delimiter = match epsv_section.chars().next() {
+			Some(v) => v,
+			None => return Err(InvalidResponseError(self.clone())),
+		};
+		let epsv_unparsed: Vec<&str> = epsv_section.split(delimiter).collect();
+		if epsv_unparsed.len() != 5 {
+			return Err(InvalidResponseError(self.clone()));
+		}
+
+		match epsv_unparsed[3].parse::<u16>() {
+			Ok(port) => Ok(port),
+			Err(_) => Err(InvalidResponseError(self.clone())),
+		}
+	}
+
+	/// Parses the name the server chose for a file stored with `STOU`.
+	///
+	/// Most servers announce the name in the reply opening the data connection,
+	/// others in the reply completing the transfer. Both commonly follow the
+	/// `FILE: name` convention, which is searched for on every line.
+	///
+	/// ## Example:
+	/// ```rust
+	/// use ftp_lib::FtpResponse;
+	///
+	/// let response: FtpResponse = "150 FILE: upload.1234".parse().unwrap();
+	/// assert_eq!(response.parse_unique_name().unwrap(), "upload.1234");
+	/// ```
+	pub fn parse_unique_name(&self) -> Result<String, FtpError> {
+		// Make sure the type being converted really is a reply to a transfer
+		match self.status {
+			ftp_status::FILE_OPENING_DATA
+			| ftp_status::DATA_TRANSFER_STARTING
+			| ftp_status::DATA_CLOSING
+			| ftp_status::FILE_ACTION_COMPLETE => (),
+			_ => return Err(InvalidTypeError),
+		}
+
+		for line in self.message().lines() {
+			// Only ASCII is uppercased, so the positions stay the same as in the line
+			if let Some(start) = line.to_ascii_uppercase().find("FILE:") {
+				let name = line[start + 5..].trim();
+				if !name.is_empty() {
+					return Ok(name.to_string());
+				}
+			}
+		}
+
+		Err(InvalidResponseError(self.clone()))
+	}
+
+	/// Parses the extensions listed in a `FEAT` response (RFC 2389).
+	///
+	/// The response is made of one line per feature, each starting with a
+	/// space, between the opening and closing lines of a multi-line reply.
+	///
+	/// ## Example:
+	/// ```rust
+	/// use ftp_lib::FtpResponse;
+	///
+	/// let response = FtpResponse {
+	///     status: 211,
+	///     content: "211-Features:\r\n EPSV\r\n AUTH TLS\r\n211 End\r\n".to_string(),
+	/// };
+	/// let features = response.parse_features().unwrap();
+	/// assert!(features.supports("EPSV"));
+	/// assert_eq!(features.auth_mechanisms(), vec!["TLS"]);
+	/// ```
+	pub fn parse_features(&self) -> Result<Features, FtpError> {
+		// Make sure the type being converted really is a FEAT response
+		if self.status != ftp_status::SYSTEM_STATUS {
+			return Err(InvalidTypeError);
+		}
+
+		Ok(Features::from_lines(&self.lines()))
+	}
+
+	/// Parses the operating system from a `SYST` response.
+	///
+	/// The first word of the response names the system, and anything after it,
+	/// such as the `Type: L8` sent by Unix servers, is ignored.
+	///
+	/// ## Example:
+	/// ```rust
+	/// use ftp_lib::{FtpResponse, SystemType};
+	///
+	/// let response: FtpResponse = "215 UNIX Type: L8".parse().unwrap();
+	/// assert_eq!(response.parse_system_type().unwrap(), SystemType::Unix);
+	///
+	/// let response: FtpResponse = "215 MVS is the operating system of this server.".parse().unwrap();
+	/// assert_eq!(response.parse_system_type().unwrap(), SystemType::Other("MVS".to_string()));
+	/// ```
+	pub fn parse_system_type(&self) -> Result<SystemType, FtpError> {
+		// Make sure the type being converted really is a SYST response
+		if self.status != ftp_status::SYSTEM_TYPE {
+			return Err(InvalidTypeError);
+		}
+
+		let message = self.message();
+		let name = match message.split_whitespace().next() {
+			Some(v) => v,
+			None => return Err(InvalidResponseFormatError),
+		};
+
+		match name.to_ascii_uppercase().as_str() {
+			"UNIX" => Ok(SystemType::Unix),
+			"WINDOWS_NT" | "WIN32" => Ok(SystemType::Windows),
+			"VMS" => Ok(SystemType::Vms),
+			_ => Ok(SystemType::Other(name.to_string())),
+		}
+	}
 }
 
 impl FromStr for FtpResponse {
@@ -151,7 +378,7 @@ impl FromStr for FtpResponse {
 }
 
 /// The state of the ftp connection, connected or not
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum FtpState {
 	/// If the connection is active
 	Connected,
@@ -159,6 +386,196 @@ pub enum FtpState {
 	Disconnected,
 }
 
+/// Limits on how long the connection waits on the FTP server
+///
+/// Each timeout is optional, and `None` waits forever. The default leaves
+/// every timeout unset. When a timeout expires, the operation fails with
+/// `FtpError::Timeout`.
+///
+/// ## Example:
+/// ```rust
+/// use ftp_lib::FtpTimeouts;
+/// use std::time::Duration;
+///
+/// let timeouts = FtpTimeouts {
+///     connect: Some(Duration::from_secs(5)),
+///     control: Some(Duration::from_secs(30)),
+///     ..FtpTimeouts::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FtpTimeouts {
+	/// How long to wait for a connection to be established, both for the
+	/// control connection and for data connections
+	pub connect: Option<Duration>,
+	/// How long to wait when reading a reply from or sending a command to the server
+	pub control: Option<Duration>,
+	/// How long a data connection may stay idle during a transfer
+	pub data: Option<Duration>,
+}
+
+/// Settings used when connecting to the FTP server
+///
+/// Made with `FtpConnection::builder`, the settings are chosen first and the
+/// connection is then opened to an address with `connect`, or to a host name
+/// with `connect_host`. `FtpConnection::connect` and its siblings use the
+/// default settings.
+///
+/// ## Example:
+/// Connect to 127.0.0.1, waiting at most 5 seconds for the server.
+/// ```rust
+/// use ftp_lib::{FtpConnection, FtpTimeouts};
+/// use std::net::SocketAddrV4;
+/// use std::time::Duration;
+///
+/// let timeouts = FtpTimeouts {
+///     connect: Some(Duration::from_secs(5)),
+///     control: Some(Duration::from_secs(5)),
+///     data: Some(Duration::from_secs(30)),
+/// };
+///
+/// let mut ftp_conn = FtpConnection::builder()
+///     .timeouts(timeouts)
+///     .connect("127.0.0.1:21".parse::<SocketAddrV4>().unwrap())
+///     .unwrap(); // Initiate the connection
+///
+/// ftp_conn.quit().unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FtpConnectionBuilder {
+	timeouts: FtpTimeouts,
+	#[cfg(feature = "tls")]
+	implicit_tls: Option<tls::TlsConfig>,
+}
+
+impl FtpConnectionBuilder {
+	/// Creates the default settings, with no timeouts and no TLS.
+	pub fn new() -> FtpConnectionBuilder {
+		FtpConnectionBuilder::default()
+	}
+
+	/// Sets the timeouts used while connecting and for the rest of the connection.
+	///
+	/// Without timeouts, connecting waits forever on an unresponsive server.
+	/// When connecting to a host name, the connect timeout applies to each
+	/// address tried rather than to all of them together. See
+	/// `FtpConnection::set_timeouts` to change them later.
+	pub fn timeouts(mut self, timeouts: FtpTimeouts) -> FtpConnectionBuilder {
+		self.timeouts = timeouts;
+		self
+	}
+
+	/// Protects the connection with TLS from the start (implicit FTPS).
+	///
+	/// See `FtpConnection::connect_implicit_tls`, the control timeout also
+	/// covers the TLS handshake.
+	#[cfg(feature = "tls")]
+	pub fn implicit_tls(mut self, tls_config: tls::TlsConfig) -> FtpConnectionBuilder {
+		self.implicit_tls = Some(tls_config);
+		self
+	}
+
+	/// Initiates the connection to the FTP server, see `FtpConnection::connect`.
+	pub fn connect(
+		&self,
+		connection_addr: impl Into<SocketAddr>,
+	) -> Result<FtpConnection, FtpError> {
+		let stream = FtpConnection::open_control_stream(connection_addr.into(), self.timeouts)?;
+
+		#[cfg(feature = "tls")]
+		{
+			if let Some(ref tls_config) = self.implicit_tls {
+				let mut ftp_conn =
+					FtpConnection::from_stream(tls_config.wrap(stream)?, self.timeouts)?;
+				ftp_conn.protect_datastreams(tls_config.clone())?;
+				return Ok(ftp_conn);
+			}
+		}
+
+		FtpConnection::from_stream(FtpStream::Plain(stream), self.timeouts)
+	}
+
+	/// Initiates the connection to the FTP server using its host name, see `FtpConnection::connect_host`.
+	pub fn connect_host(&self, host: &str, port: u16) -> Result<FtpConnection, FtpError> {
+		let connection_addrs = match (host, port).to_socket_addrs() {
+			Ok(v) => v,
+			Err(_) => return Err(HostResolutionError(host.to_string())),
+		};
+
+		// Try every address, keeping track of why each one failed
+		let mut failures = vec![];
+		for connection_addr in connection_addrs {
+			match self.connect(connection_addr) {
+				Ok(ftp_conn) => return Ok(ftp_conn),
+				Err(e) => failures.push((connection_addr, e)),
+			}
+		}
+
+		if failures.is_empty() {
+			Err(HostResolutionError(host.to_string()))
+		} else {
+			Err(ConnectionAttemptsFailed(failures))
+		}
+	}
+}
+
+/// How data connections are established for transfers and listings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataMode {
+	/// The server listens for the data connection (`PASV`)
+	///
+	/// `EPSV` is used instead when connected over IPv6.
+	Passive,
+	/// The server listens for the data connection (`EPSV`, RFC 2428)
+	///
+	/// Falls back to `Passive` for good when the server does not know `EPSV`.
+	ExtendedPassive,
+	/// The client listens for the data connection (`PORT`)
+	///
+	/// `EPRT` is used instead when connected over IPv6. In both active modes,
+	/// connections from any address other than the server's are refused.
+	Active,
+	/// The client listens for the data connection (`EPRT`, RFC 2428)
+	ExtendedActive,
+}
+
+/// The representation type used for file transfers (`TYPE`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferType {
+	/// Text using CRLF line endings on the wire (`TYPE A`)
+	Ascii,
+	/// Text encoded in EBCDIC (`TYPE E`)
+	Ebcdic,
+	/// Bytes sent exactly as they are, often called binary mode (`TYPE I`)
+	Image,
+	/// Bytes of the given logical size (`TYPE L n`)
+	Local(u8),
+}
+
+/// The operating system of the FTP server, as reported by `SYST`
+///
+/// The system type hints at the format of `LIST` output, so it can be used to
+/// choose a parser for `FtpConnection::set_list_parser`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SystemType {
+	/// A Unix-like system (`UNIX`)
+	Unix,
+	/// Microsoft Windows (`Windows_NT`)
+	Windows,
+	/// OpenVMS (`VMS`)
+	Vms,
+	/// Any other system, with the name as sent by the server, such as `MVS`
+	Other(String),
+}
+
+/// A data connection which has been negotiated but not yet opened
+enum PendingDatastream {
+	/// Connect to the server at the given address
+	Connect(SocketAddr),
+	/// Wait for the server to connect to the listener
+	Listen(TcpListener),
+}
+
 /// The main type used for communication with the FTP server.
 ///
 /// Once calling the constructor a connection to the specified socket address
@@ -171,15 +588,25 @@ pub enum FtpState {
 /// use std::net::SocketAddrV4;
 ///
 /// let mut ftp_conn = FtpConnection::connect(
-///     "127.0.0.1:21".parse().unwrap()
+///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 /// ).unwrap(); // Initiate the connection
 ///
 /// ftp_conn.quit().unwrap();
 /// ```
 #[derive(Debug)]
 pub struct FtpConnection {
-	reader: BufReader<TcpStream>,
+	reader: BufReader<FtpStream>,
 	state: FtpState,
+	data_mode: DataMode,
+	transfer_type: Option<TransferType>,
+	timeouts: FtpTimeouts,
+	keepalive: Option<Duration>,
+	last_activity: Instant,
+	features: Option<Features>,
+	list_parser: Box<dyn ListParser>,
+	preserve_modified_time: bool,
+	#[cfg(feature = "tls")]
+	tls_config: Option<tls::TlsConfig>,
 }
 
 impl FtpConnection {
@@ -191,6 +618,11 @@ impl FtpConnection {
 	/// the function will return a functional instance of FtpConnection ready for
 	/// varius operations to be performed.
 	///
+	/// Both IPv4 and IPv6 addresses are accepted, as a `SocketAddr`,
+	/// `SocketAddrV4` or `SocketAddrV6`. Over IPv6 the data connections are
+	/// negotiated with `EPSV` and `EPRT`, as `PASV` and `PORT` cannot describe
+	/// IPv6 addresses.
+	///
 	/// ## Example:
 	/// Connect to 127.0.0.1 then close the connection.
 	/// ```rust
@@ -198,42 +630,258 @@ impl FtpConnection {
 	/// use std::net::SocketAddrV4;
 	///
 	/// let mut ftp_conn = FtpConnection::connect(
-	///     "127.0.0.1:21".parse().unwrap()
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap(); // Initiate the connection
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	///
+	/// Connect to ::1 then close the connection.
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV6;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "[::1]:21".parse::<SocketAddrV6>().unwrap()
 	/// ).unwrap(); // Initiate the connection
 	///
 	/// ftp_conn.quit().unwrap();
 	/// ```
-	pub fn connect(connection_addr: SocketAddrV4) -> Result<FtpConnection, FtpError> {
+	pub fn connect(connection_addr: impl Into<SocketAddr>) -> Result<FtpConnection, FtpError> {
+		FtpConnectionBuilder::new().connect(connection_addr)
+	}
+
+	/// Chooses the settings for a new connection, such as its timeouts.
+	///
+	/// ## Example:
+	/// Connect to localhost, waiting at most 5 seconds for each address.
+	/// ```rust
+	/// use ftp_lib::{FtpConnection, FtpTimeouts};
+	/// use std::time::Duration;
+	///
+	/// let timeouts = FtpTimeouts {
+	///     connect: Some(Duration::from_secs(5)),
+	///     ..FtpTimeouts::default()
+	/// };
+	///
+	/// let mut ftp_conn = FtpConnection::builder()
+	///     .timeouts(timeouts)
+	///     .connect_host("localhost", 21)
+	///     .unwrap(); // Initiate the connection
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn builder() -> FtpConnectionBuilder {
+		FtpConnectionBuilder::new()
+	}
+
+	/// Initiates the connection to the FTP server using its host name.
+	///
+	/// The host name is resolved and each of the addresses it resolves to
+	/// is tried in turn, until one of them accepts the connection the same way
+	/// `connect` does. If none of them work, the returned error contains every
+	/// address tried along with the reason it failed.
+	///
+	/// ## Example:
+	/// Connect to localhost then close the connection.
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	///
+	/// let mut ftp_conn = FtpConnection::connect_host("localhost", 21).unwrap(); // Initiate the connection
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn connect_host(host: &str, port: u16) -> Result<FtpConnection, FtpError> {
+		FtpConnectionBuilder::new().connect_host(host, port)
+	}
+
+	fn open_control_stream(
+		connection_addr: SocketAddr,
+		timeouts: FtpTimeouts,
+	) -> Result<TcpStream, FtpError> {
 		// Initiate connection to the FTP server
-		match TcpStream::connect(connection_addr) {
-			Ok(stream) => {
-				//  Initiate a new instance for user use.
-				let mut ftp_conn = FtpConnection {
-					reader: BufReader::new(stream),
-					state: FtpState::Connected,
-				};
+		let connected = match timeouts.connect {
+			Some(timeout) => TcpStream::connect_timeout(&connection_addr, timeout),
+			None => TcpStream::connect(connection_addr),
+		};
+		let stream = match connected {
+			Ok(v) => v,
+			Err(e) => return Err(FtpError::from_io_error(&e, ConnectionError)),
+		};
+
+		match stream
+			.set_read_timeout(timeouts.control)
+			.and_then(|_| stream.set_write_timeout(timeouts.control))
+		{
+			Ok(_) => Ok(stream),
+			Err(_) => Err(ConnectionError),
+		}
+	}
 
+	fn from_stream(stream: FtpStream, timeouts: FtpTimeouts) -> Result<FtpConnection, FtpError> {
+		//  Initiate a new instance for user use.
+		let mut ftp_conn = FtpConnection {
+			reader: BufReader::new(stream),
+			state: FtpState::Connected,
+			data_mode: DataMode::Passive,
+			transfer_type: None,
+			timeouts,
+			keepalive: None,
+			last_activity: Instant::now(),
+			features: None,
+			list_parser: Box::new(listparser::AutoParser),
+			preserve_modified_time: false,
+			#[cfg(feature = "tls")]
+			tls_config: None,
+		};
+
+		let res = ftp_conn.wait_for_response()?;
+
+		// TODO: Missing other possible responses
+		match res.status {
+			// Successful connection
+			ftp_status::SERVICE_READY => Ok(ftp_conn),
+			// Error connecting
+			ftp_status::READY_IN => {
 				let res = ftp_conn.wait_for_response()?;
 
-				// TODO: Missing other possible responses
 				match res.status {
-					// Successful connection
 					ftp_status::SERVICE_READY => Ok(ftp_conn),
-					// Error connecting
-					ftp_status::READY_IN => {
-						let res = ftp_conn.wait_for_response()?;
-
-						match res.status {
-							ftp_status::SERVICE_READY => Ok(ftp_conn),
-							_ => Err(ServiceNotReady),
-						}
-					}
-					ftp_status::SERVICE_NOT_AVAILABLE => Err(ServiceUnavailable),
-					_ => Err(InvalidResponseError(res)),
+					_ => Err(ServiceNotReady),
 				}
 			}
-			Err(_) => Err(ConnectionError),
+			ftp_status::SERVICE_NOT_AVAILABLE => Err(ServiceUnavailable),
+			_ => Err(InvalidResponseError(res)),
+		}
+	}
+
+	/// Protects the connection to the FTP server with TLS (explicit FTPS).
+	///
+	/// The control connection is upgraded using `AUTH TLS` (RFC 4217), after
+	/// which `PBSZ 0` and `PROT P` are sent so every data connection opened
+	/// for listings and transfers is protected as well. This should be called
+	/// before `login` so the credentials are never sent in plain text. A
+	/// connection that is already protected, including one made with
+	/// `connect_implicit_tls`, returns `FtpError::TlsError`.
+	///
+	/// ## Example:
+	/// Connect to localhost, secure the connection, then login.
+	/// ```rust,no_run
+	/// use ftp_lib::rustls::{ClientConfig, RootCertStore};
+	/// use ftp_lib::tls::TlsConfig;
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	/// use std::sync::Arc;
+	///
+	/// let mut roots = RootCertStore::empty();
+	/// roots.add(std::fs::read("ca.der").unwrap().into()).unwrap();
+	/// let config = ClientConfig::builder()
+	///     .with_root_certificates(roots)
+	///     .with_no_client_auth();
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// // Upgrade the connection before sending any credentials
+	/// ftp_conn.secure(TlsConfig::new(Arc::new(config), "localhost").unwrap()).unwrap();
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	#[cfg(feature = "tls")]
+	pub fn secure(&mut self, tls_config: tls::TlsConfig) -> Result<(), FtpError> {
+		if let FtpStream::Tls(_) = self.reader.get_ref() {
+			return Err(TlsError(
+				"The connection is already protected with TLS".to_string(),
+			));
+		}
+
+		self.keep_alive()?;
+
+		let command = "AUTH TLS\r\n".to_string();
+		self.write_command(command.clone())?;
+
+		let auth_result = self.wait_for_response()?;
+		match auth_result.status {
+			// Successful action
+			ftp_status::SECURITY_EXCHANGE_COMPLETE => (),
+			// Error completing action
+			_ => return Err(FtpError::from_status_code(auth_result, command)),
+		}
+
+		// Nothing else is sent by the server until the handshake is made
+		let stream = match self.reader.get_ref().get_ref().try_clone() {
+			Ok(v) => v,
+			Err(_) => return Err(ConnectionError),
+		};
+		self.reader = BufReader::new(tls_config.wrap(stream)?);
+
+		// Servers may advertise different features once the connection is protected
+		self.features = None;
+		self.protect_datastreams(tls_config)
+	}
+
+	/// Initiates a connection to the FTP server protected with TLS from the start (implicit FTPS).
+	///
+	/// Some servers, usually listening on port 990, expect the TLS handshake
+	/// immediately after connecting rather than waiting for `AUTH TLS`. Once the
+	/// handshake is made the server's greeting is handled the same way `connect`
+	/// does, and `PBSZ 0` and `PROT P` are sent so every data connection is
+	/// protected as well.
+	///
+	/// ## Example:
+	/// Connect to localhost on port 990 then login.
+	/// ```rust,no_run
+	/// use ftp_lib::rustls::{ClientConfig, RootCertStore};
+	/// use ftp_lib::tls::TlsConfig;
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	/// use std::sync::Arc;
+	///
+	/// let mut roots = RootCertStore::empty();
+	/// roots.add(std::fs::read("ca.der").unwrap().into()).unwrap();
+	/// let config = ClientConfig::builder()
+	///     .with_root_certificates(roots)
+	///     .with_no_client_auth();
+	///
+	/// let mut ftp_conn = FtpConnection::connect_implicit_tls(
+	///     "127.0.0.1:990".parse::<SocketAddrV4>().unwrap(),
+	///     TlsConfig::new(Arc::new(config), "localhost").unwrap()
+	/// ).unwrap(); // Initiate the connection
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	#[cfg(feature = "tls")]
+	pub fn connect_implicit_tls(
+		connection_addr: impl Into<SocketAddr>,
+		tls_config: tls::TlsConfig,
+	) -> Result<FtpConnection, FtpError> {
+		FtpConnectionBuilder::new()
+			.implicit_tls(tls_config)
+			.connect(connection_addr)
+	}
+
+	#[cfg(feature = "tls")]
+	fn protect_datastreams(&mut self, tls_config: tls::TlsConfig) -> Result<(), FtpError> {
+		// PBSZ is required before PROT, but is always 0 for TLS
+		for command in ["PBSZ 0\r\n", "PROT P\r\n"].iter() {
+			let command = command.to_string();
+			self.write_command(command.clone())?;
+
+			let res = self.wait_for_response()?;
+			match res.status {
+				// Successful action
+				ftp_status::COMMAND_OKAY => (),
+				// Error completing action
+				_ => return Err(FtpError::from_status_code(res, command)),
+			}
 		}
+
+		self.tls_config = Some(tls_config);
+		Ok(())
 	}
 
 	/// Authenticates the connection to the FTP server.
@@ -251,7 +899,7 @@ impl FtpConnection {
 	/// use std::net::SocketAddrV4;
 	///
 	/// let mut ftp_conn = FtpConnection::connect(
-	///     "127.0.0.1:21".parse().unwrap()
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 	/// ).unwrap();
 	///
 	/// // login(username, password)
@@ -260,6 +908,11 @@ impl FtpConnection {
 	/// ftp_conn.quit().unwrap();
 	/// ```
 	pub fn login(&mut self, username: &str, password: Option<&str>) -> Result<(), FtpError> {
+		self.keep_alive()?;
+
+		// Servers may advertise different features once logged in
+		self.features = None;
+
 		let command = format!("USER {}\r\n", username);
 		self.write_command(command.clone())?;
 
@@ -268,7 +921,7 @@ impl FtpConnection {
 		match user_result.status {
 			// Successful action
 			ftp_status::PASSWORD_NEEDED => {
-				let command = format!("PASS {}\r\n", password.unwrap_or_else(|| ""));
+				let command = format!("PASS {}\r\n", password.unwrap_or(""));
 				self.write_command(command.clone())?;
 				let pass_result = self.wait_for_response()?;
 
@@ -298,7 +951,7 @@ impl FtpConnection {
 	/// use std::net::SocketAddrV4;
 	///
 	/// let mut ftp_conn = FtpConnection::connect(
-	///     "127.0.0.1:21".parse().unwrap()
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 	/// ).unwrap();
 	///
 	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
@@ -310,44 +963,284 @@ impl FtpConnection {
 	pub fn quit(&mut self) -> Result<(), FtpError> {
 		let command = "QUIT\r\n".to_string();
 		self.write_command(command)?;
+		self.state = FtpState::Disconnected;
 
 		// Shut the connection down even if the server does not respond nicely
-		match self.reader.get_mut().shutdown(std::net::Shutdown::Both) {
+		match self.reader.get_mut().shutdown() {
 			Ok(_) => Ok(()),
 			Err(_) => Err(ConnectionError),
 		}
 	}
 
-	/// Changes the current working directory in the FTP server.
+	/// Sets how data connections are established with the FTP server.
 	///
-	/// Works similarly to the `cd` command in shells. On successful
-	/// completion of this command, `pwd` should often follow to be
-	/// absolutely sure the program knows where it currently is, this
-	/// is due to the fact that the client cannot be certain of how the
-	/// server will behave in response.
+	/// By default the connection uses passive mode, where the server listens
+	/// for the data connection. Active mode makes the client listen instead,
+	/// which can be required by servers that sit behind restrictive firewalls.
+	/// The extended modes use the `EPSV` and `EPRT` commands from RFC 2428.
+	///
+	/// If the server does not understand `EPSV`, extended passive mode falls
+	/// back to passive mode for the rest of the connection.
 	///
 	/// ## Example:
-	/// Connect to 127.0.0.1 then change to the /test_dir directory.
+	/// Connect to localhost then list the files using active mode
 	/// ```rust
-	/// use ftp_lib::FtpConnection;
+	/// use ftp_lib::{DataMode, FtpConnection};
 	/// use std::net::SocketAddrV4;
 	///
 	/// let mut ftp_conn = FtpConnection::connect(
-	///     "127.0.0.1:21".parse().unwrap()
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 	/// ).unwrap();
 	///
 	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
 	///
-	/// # // Create the directory
-	/// # ftp_conn.mkdir("test_dir").unwrap();
-	/// ftp_conn.cd("test_dir").unwrap(); // Change the working directory to rfc
-	/// # // Remove dir
-	/// # ftp_conn.cdup().unwrap();
-	/// # ftp_conn.rmdir("test_dir").unwrap();
+	/// ftp_conn.set_data_mode(DataMode::Active); // Let the server connect to the client
+	/// println!("{:?}", ftp_conn.name_list().unwrap());
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn set_data_mode(&mut self, data_mode: DataMode) {
+		self.data_mode = data_mode;
+	}
+
+	/// Gets how data connections are established with the FTP server.
+	pub fn data_mode(&self) -> DataMode {
+		self.data_mode
+	}
+
+	/// Sets the representation type used for file transfers.
+	///
+	/// Until this is called the server's default is used, which is often ASCII.
+	/// ASCII transfers may change line endings, so binary files should be
+	/// transferred with `TransferType::Image`. `fetch_file` and `write_file`
+	/// switch to `TransferType::Image` on their own, while the streaming
+	/// transfers use whichever type was set last.
+	///
+	/// ## Example:
+	/// Connect to localhost then download README.txt in binary mode
+	/// ```rust
+	/// use ftp_lib::{FtpConnection, TransferType};
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # ftp_conn.write_file("README.txt", "This is the README.txt file. Cool, right?".as_bytes().to_vec()).unwrap();
+	/// ftp_conn.transfer_type(TransferType::Image).unwrap(); // Transfer the file unchanged
+	/// let mut reader = ftp_conn.retr_reader("README.txt").unwrap();
+	/// std::io::copy(&mut reader, &mut std::io::stdout()).unwrap();
+	/// reader.finish().unwrap();
+	/// # ftp_conn.rm("README.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn transfer_type(&mut self, transfer_type: TransferType) -> Result<(), FtpError> {
+		self.keep_alive()?;
+
+		let command = match transfer_type {
+			TransferType::Ascii => "TYPE A\r\n".to_string(),
+			TransferType::Ebcdic => "TYPE E\r\n".to_string(),
+			TransferType::Image => "TYPE I\r\n".to_string(),
+			TransferType::Local(byte_size) => format!("TYPE L {}\r\n", byte_size),
+		};
+		self.write_command(command.clone())?;
+
+		let type_result = self.wait_for_response()?;
+		match type_result.status {
+			// Successful action
+			ftp_status::COMMAND_OKAY => {
+				self.transfer_type = Some(transfer_type);
+				Ok(())
+			}
+			// Error completing action
+			_ => Err(FtpError::from_status_code(type_result, command)),
+		}
+	}
+
+	/// Gets the representation type last set with `transfer_type`.
+	///
+	/// Returns `None` if no type has been set, meaning the server's default is used.
+	pub fn current_transfer_type(&self) -> Option<TransferType> {
+		self.transfer_type
+	}
+
+	/// Sets the timeouts used for the rest of the connection.
+	///
+	/// The control timeout applies immediately, while the connect and data
+	/// timeouts apply to the next data connection opened.
+	///
+	/// ## Example:
+	/// Connect to localhost then stop waiting forever for replies
+	/// ```rust
+	/// use ftp_lib::{FtpConnection, FtpTimeouts};
+	/// use std::net::SocketAddrV4;
+	/// use std::time::Duration;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.set_timeouts(FtpTimeouts {
+	///     control: Some(Duration::from_secs(10)),
+	///     ..FtpTimeouts::default()
+	/// }).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn set_timeouts(&mut self, timeouts: FtpTimeouts) -> Result<(), FtpError> {
+		let stream = self.reader.get_ref().get_ref();
+		match stream
+			.set_read_timeout(timeouts.control)
+			.and_then(|_| stream.set_write_timeout(timeouts.control))
+		{
+			Ok(_) => (),
+			Err(_) => return Err(ConnectionError),
+		}
+
+		self.timeouts = timeouts;
+		Ok(())
+	}
+
+	/// Gets the timeouts used by the connection.
+	pub fn timeouts(&self) -> FtpTimeouts {
+		self.timeouts
+	}
+
+	/// Sets how long the control connection may stay idle before a `NOOP` is sent.
+	///
+	/// Servers close connections that stay idle for too long. With a keep-alive
+	/// interval set, a `NOOP` is sent at the start of the next method talking
+	/// to the server whenever nothing was sent or received for longer than the
+	/// interval, which resets the server's idle timer. Commands which belong
+	/// together, such as `REST` and `RETR`, are never split by it. No thread is
+	/// used, so the interval must be shorter than the server's idle timeout.
+	/// `None` turns the keep-alive off, which is the default.
+	///
+	/// ## Example:
+	/// Connect to localhost then keep the connection alive between batches
+	/// ```rust,no_run
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	/// use std::time::Duration;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// ftp_conn.set_keepalive(Some(Duration::from_secs(60)));
+	/// println!("{:?}", ftp_conn.list().unwrap());
+	/// std::thread::sleep(Duration::from_secs(90));
+	/// println!("{:?}", ftp_conn.list().unwrap()); // Preceded by a NOOP
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn set_keepalive(&mut self, interval: Option<Duration>) {
+		self.keepalive = interval;
+	}
+
+	/// Gets how long the control connection may stay idle before a `NOOP` is sent.
+	pub fn keepalive(&self) -> Option<Duration> {
+		self.keepalive
+	}
+
+	/// Gets whether the connection to the FTP server is still open.
+	///
+	/// The connection becomes `FtpState::Disconnected` after `quit`, when the
+	/// server replies with 421 because it is closing the connection, such as
+	/// after its idle timeout, or when the server is found to have closed it.
+	pub fn state(&self) -> FtpState {
+		self.state
+	}
+
+	/// Gets the extensions supported by the FTP server.
+	///
+	/// Sends `FEAT` (RFC 2389) the first time it is called and keeps the
+	/// result for the rest of the connection, so later calls don't talk to the
+	/// server. The result is cleared by `login` and `secure`, since servers may
+	/// advertise more features after them. Servers which do not implement
+	/// `FEAT` are treated as supporting no extensions.
+	///
+	/// ## Example:
+	/// Connect to localhost then check for `MLST` support
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// let features = ftp_conn.features().unwrap();
+	/// println!("MLST supported: {}", features.supports("MLST"));
+	/// println!("MLST facts: {:?}", features.mlst_facts());
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn features(&mut self) -> Result<&Features, FtpError> {
+		let features = match self.features.take() {
+			Some(features) => features,
+			None => {
+				self.keep_alive()?;
+
+				let command = "FEAT\r\n".to_string();
+				self.write_command(command.clone())?;
+
+				let feat_result = self.wait_for_response()?;
+				match feat_result.status {
+					// Successful action
+					ftp_status::SYSTEM_STATUS => feat_result.parse_features()?,
+					// The server predates FEAT (500 or 502), so it has no extensions to list
+					ftp_status::SYNTAX_ERROR | ftp_status::COMMAND_NOT_IMPLEMENTED => Features::default(),
+					// Error completing action
+					_ => return Err(FtpError::from_status_code(feat_result, command)),
+				}
+			}
+		};
+
+		Ok(self.features.insert(features))
+	}
+
+	/// Changes the current working directory in the FTP server.
+	///
+	/// Works similarly to the `cd` command in shells. On successful
+	/// completion of this command, `pwd` should often follow to be
+	/// absolutely sure the program knows where it currently is, this
+	/// is due to the fact that the client cannot be certain of how the
+	/// server will behave in response.
+	///
+	/// ## Example:
+	/// Connect to 127.0.0.1 then change to the /test_dir directory.
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # // Create the directory
+	/// # ftp_conn.mkdir("test_dir").unwrap();
+	/// ftp_conn.cd("test_dir").unwrap(); // Change the working directory to rfc
+	/// # // Remove dir
+	/// # ftp_conn.cdup().unwrap();
+	/// # ftp_conn.rmdir("test_dir").unwrap();
 	///
 	/// ftp_conn.quit().unwrap();
 	/// ```
 	pub fn cd(&mut self, path: &str) -> Result<(), FtpError> {
+		self.keep_alive()?;
+
 		let command = format!("CWD {}\r\n", path);
 		self.write_command(command.clone())?;
 
@@ -373,7 +1266,7 @@ impl FtpConnection {
 	/// use std::net::SocketAddrV4;
 	///
 	/// let mut ftp_conn = FtpConnection::connect(
-	///     "127.0.0.1:21".parse().unwrap()
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 	/// ).unwrap();
 	///
 	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
@@ -391,6 +1284,8 @@ impl FtpConnection {
 	/// ftp_conn.quit().unwrap();
 	/// ```
 	pub fn cdup(&mut self) -> Result<(), FtpError> {
+		self.keep_alive()?;
+
 		let command = "CDUP\r\n".to_string();
 		self.write_command(command.clone())?;
 
@@ -418,7 +1313,7 @@ impl FtpConnection {
 	/// use std::net::SocketAddrV4;
 	///
 	/// let mut ftp_conn = FtpConnection::connect(
-	///     "127.0.0.1:21".parse().unwrap()
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 	/// ).unwrap();
 	///
 	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
@@ -428,6 +1323,8 @@ impl FtpConnection {
 	/// ftp_conn.quit().unwrap();
 	/// ```
 	pub fn pwd(&mut self) -> Result<String, FtpError> {
+		self.keep_alive()?;
+
 		let command = "PWD\r\n".to_string();
 		self.write_command(command.clone())?;
 
@@ -454,8 +1351,9 @@ impl FtpConnection {
 	/// Listing the files in the directory can be a difficult task,
 	/// if the server being used does not follow the common output,
 	/// the program may not be able to understand the current directory.
-	/// If there is an issue, another directory list structure may need to
-	/// be implemented.
+	/// The Unix, DOS, VMS and EPLF formats are detected automatically, and
+	/// `set_list_parser` allows other formats to be read. A line which can't
+	/// be parsed results in `FtpError::ListingParseError`.
 	///
 	/// ## Example:
 	/// Lists all files in /
@@ -464,7 +1362,7 @@ impl FtpConnection {
 	/// use std::net::SocketAddrV4;
 	///
 	/// let mut ftp_conn = FtpConnection::connect(
-	///     "127.0.0.1:21".parse().unwrap()
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 	/// ).unwrap();
 	///
 	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
@@ -478,43 +1376,114 @@ impl FtpConnection {
 	/// ftp_conn.quit().unwrap();
 	/// ```
 	pub fn list(&mut self) -> Result<Vec<filestructure::DirectoryItem>, FtpError> {
-		let datastream_addr = self.pasv()?;
+		self.keep_alive()?;
 
 		let command = "LIST\r\n".to_string();
-		self.write_command(command.clone())?;
+		let (datavec, _) = self.read_datastream(command)?;
 
-		let datavec = self.connect_datastream(datastream_addr)?;
+		self.list_parser.parse_listing(&String::from_utf8_lossy(&datavec))
+	}
 
-		let res = self.wait_for_response()?;
-		match res.status {
-			// Successful action
-			ftp_status::FILE_OPENING_DATA | ftp_status::DATA_TRANSFER_STARTING => (),
-			// Error completing action
-			_ => return Err(FtpError::from_status_code(res, command)),
-		}
+	/// Lists files in the current directory, keeping the lines which could not be parsed
+	///
+	/// Works like `list`, but a line the parser does not understand is
+	/// returned as `ListEntry::Raw` instead of failing the whole listing.
+	///
+	/// ## Example:
+	/// Lists all files in /, showing the lines which were not understood
+	/// ```rust
+	/// use ftp_lib::listparser::ListEntry;
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// for entry in ftp_conn.list_lenient().unwrap() {
+	///     match entry {
+	///         ListEntry::Parsed(item) => println!("{}", item.name()),
+	///         ListEntry::Raw(line) => println!("Not understood: {}", line),
+	///     }
+	/// }
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn list_lenient(&mut self) -> Result<Vec<listparser::ListEntry>, FtpError> {
+		self.keep_alive()?;
 
-		let res = self.wait_for_response()?;
-		match res.status {
-			// Successful action
-			ftp_status::DATA_CLOSING | ftp_status::FILE_ACTION_COMPLETE => (),
-			// Error completing action
-			_ => return Err(FtpError::from_status_code(res, command)),
-		}
+		let command = "LIST\r\n".to_string();
+		let (datavec, _) = self.read_datastream(command)?;
 
-		// FIXME: Rewrite this?
-		let files: Vec<String> = String::from_utf8_lossy(&datavec)
-			.split("\r\n")
-			.filter(|&x| x != "")
-			.map(|x| x.to_string())
-			.collect();
-		let mut file_output = vec![];
-		for file in files {
-			match filestructure::DirectoryItem::from_str(&file) {
-				Ok(v) => file_output.push(v),
-				Err(_) => return Err(InvalidResponseError(res)),
-			}
-		}
-		Ok(file_output)
+		Ok(self
+			.list_parser
+			.parse_listing_lenient(&String::from_utf8_lossy(&datavec)))
+	}
+
+	/// Lists files in the current directory without parsing them
+	///
+	/// The lines of the `LIST` output are returned as they were sent, only
+	/// without their line endings.
+	///
+	/// ## Example:
+	/// Lists all files in /
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// for line in ftp_conn.raw_list().unwrap() {
+	///     println!("{}", line);
+	/// }
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn raw_list(&mut self) -> Result<Vec<String>, FtpError> {
+		self.keep_alive()?;
+
+		let command = "LIST\r\n".to_string();
+		let (datavec, _) = self.read_datastream(command)?;
+
+		Ok(String::from_utf8_lossy(&datavec)
+			.lines()
+			.map(String::from)
+			.collect())
+	}
+
+	/// Sets the parser used to read the output of `list`.
+	///
+	/// By default the format is detected with `listparser::AutoParser`, which
+	/// knows the Unix, DOS, VMS and EPLF formats. Setting one of the built-in
+	/// parsers skips the detection, while a custom `ListParser` allows other
+	/// formats to be read.
+	///
+	/// ## Example:
+	/// Connect to an IIS server then list the files in the current directory
+	/// ```rust
+	/// use ftp_lib::listparser::DosParser;
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// ftp_conn.set_list_parser(DosParser);
+	/// println!("{:?}", ftp_conn.list().unwrap());
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn set_list_parser<P: ListParser + 'static>(&mut self, parser: P) {
+		self.list_parser = Box::new(parser);
 	}
 
 	/// Lists files in the current directory (Names only)
@@ -530,7 +1499,7 @@ impl FtpConnection {
 	/// use std::net::SocketAddrV4;
 	///
 	/// let mut ftp_conn = FtpConnection::connect(
-	///     "127.0.0.1:21".parse().unwrap()
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 	/// ).unwrap();
 	///
 	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
@@ -544,28 +1513,10 @@ impl FtpConnection {
 	/// ftp_conn.quit().unwrap();
 	/// ```
 	pub fn name_list(&mut self) -> Result<Vec<String>, FtpError> {
-		let datastream_addr = self.pasv()?;
+		self.keep_alive()?;
 
 		let command = "NLST\r\n".to_string();
-		self.write_command(command.clone())?;
-
-		let datavec = self.connect_datastream(datastream_addr)?;
-
-		let res = self.wait_for_response()?;
-		match res.status {
-			// Successful action
-			ftp_status::FILE_OPENING_DATA | ftp_status::DATA_TRANSFER_STARTING => (),
-			// Error completing action
-			_ => return Err(FtpError::from_status_code(res, command)),
-		}
-
-		let res = self.wait_for_response()?;
-		match res.status {
-			// Successful action
-			ftp_status::DATA_CLOSING | ftp_status::FILE_ACTION_COMPLETE => (),
-			// Error completing action
-			_ => return Err(FtpError::from_status_code(res, command)),
-		}
+		let (datavec, _) = self.read_datastream(command)?;
 
 		let output_vec = String::from_utf8_lossy(&datavec)
 			.trim_end()
@@ -576,293 +1527,1746 @@ impl FtpConnection {
 		Ok(output_vec)
 	}
 
-	/// Creates a new directory on the FTP server.
+	/// Lists the contents of a directory in the machine readable format of `MLSD` (RFC 3659).
 	///
-	/// E## xample:
-	/// Connect to localhost then create the directory cool_directory
+	/// Unlike `list`, the format of each entry is standardised, so details such
+	/// as the size and modification time don't need to be guessed from `ls`
+	/// output. When no path is given the current working directory is listed.
+	/// Any line the server sends which is not a valid entry results in
+	/// `FtpError::ListingParseError`.
+	///
+	/// ## Example:
+	/// Connect to localhost then list the sizes of the files in the current directory
 	/// ```rust
+	/// use ftp_lib::filestructure::MlsEntryType;
 	/// use ftp_lib::FtpConnection;
 	/// use std::net::SocketAddrV4;
 	///
 	/// let mut ftp_conn = FtpConnection::connect(
-	///     "127.0.0.1:21".parse().unwrap()
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 	/// ).unwrap();
 	///
 	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
 	///
-	/// ftp_conn.mkdir("cool_directory").unwrap(); // Create the directory cool_directory
-	/// println!("{:?}", ftp_conn.list().unwrap()); // Show the files in the current directory
-	/// # // Cleanup
-	/// # ftp_conn.rmdir("cool_directory").unwrap();
+	/// for entry in ftp_conn.mlsd(None).unwrap() {
+	///     if entry.entry_type() == Some(&MlsEntryType::File) {
+	///         println!("{}: {:?} bytes", entry.name(), entry.size());
+	///     }
+	/// }
 	///
 	/// ftp_conn.quit().unwrap();
 	/// ```
-	pub fn mkdir(&mut self, dir_name: &str) -> Result<(), FtpError> {
-		let command = format!("MKD {}\r\n", dir_name);
-		self.write_command(command.clone())?;
+	pub fn mlsd(&mut self, path: Option<&str>) -> Result<Vec<filestructure::MlsEntry>, FtpError> {
+		self.keep_alive()?;
 
-		let mkd_result = self.wait_for_response()?;
+		let command = match path {
+			Some(path) => format!("MLSD {}\r\n", path),
+			None => "MLSD\r\n".to_string(),
+		};
+		let (datavec, _) = self.read_datastream(command)?;
 
-		match mkd_result.status {
-			// Successful action
-			ftp_status::DIRECTORY_CREATED => Ok(()),
-			// Error completing action
-			_ => Err(FtpError::from_status_code(mkd_result, command)),
-		}
+		filestructure::parse_mlsd(&String::from_utf8_lossy(&datavec))
 	}
 
-	/// Removees a directory on the FTP server.
+	/// Gets the details of a single file or directory in the machine readable format of `MLST` (RFC 3659).
+	///
+	/// The entry is sent over the control connection, so no data connection is
+	/// needed. When no path is given the current working directory is described.
 	///
 	/// ## Example:
-	/// Connect to localhost then close the connection.
+	/// Connect to localhost then show the facts about README.txt
 	/// ```rust
 	/// use ftp_lib::FtpConnection;
 	/// use std::net::SocketAddrV4;
 	///
 	/// let mut ftp_conn = FtpConnection::connect(
-	///     "127.0.0.1:21".parse().unwrap()
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 	/// ).unwrap();
 	///
 	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
 	///
-	/// # // Create directory to remove
-	/// # ftp_conn.mkdir("test_dir").unwrap();
-	/// println!("{:?}", ftp_conn.list().unwrap()); // Show before
-	/// ftp_conn.rmdir("test_dir").unwrap(); // Remove the directory
-	/// println!("{:?}", ftp_conn.list().unwrap()); // Show after
+	/// # ftp_conn.write_file("README.txt", "This is the README.txt file. Cool, right?".as_bytes().to_vec()).unwrap();
+	/// let entry = ftp_conn.mlst(Some("README.txt")).unwrap();
+	/// println!("{:?} {:?}", entry.size(), entry.modified());
+	/// # ftp_conn.rm("README.txt").unwrap();
 	///
 	/// ftp_conn.quit().unwrap();
 	/// ```
-	pub fn rmdir(&mut self, directory: &str) -> Result<(), FtpError> {
-		let command = format!("RMD {}\r\n", directory);
+	pub fn mlst(&mut self, path: Option<&str>) -> Result<filestructure::MlsEntry, FtpError> {
+		self.keep_alive()?;
+
+		let command = match path {
+			Some(path) => format!("MLST {}\r\n", path),
+			None => "MLST\r\n".to_string(),
+		};
 		self.write_command(command.clone())?;
 
-		let rmd_result = self.wait_for_response()?;
-		match rmd_result.status {
+		let mlst_result = self.wait_for_response()?;
+		match mlst_result.status {
 			// Successful action
-			ftp_status::FILE_ACTION_COMPLETE => Ok(()),
+			ftp_status::FILE_ACTION_COMPLETE => {
+				// The entry is the line between the first and last, indented by a single space
+				let lines = mlst_result.lines();
+				let entry_lines = lines.len().saturating_sub(2);
+				for (index, line) in lines.iter().enumerate().skip(1).take(entry_lines) {
+					if let Some(entry) = line.strip_prefix(' ') {
+						return match entry.parse() {
+							Ok(v) => Ok(v),
+							Err(e) => Err(ListingParseError(index + 1, e)),
+						};
+					}
+				}
+				Err(InvalidResponseError(mlst_result))
+			}
 			// Error completing action
-			_ => Err(FtpError::from_status_code(rmd_result, command)),
+			_ => Err(FtpError::from_status_code(mlst_result, command)),
 		}
 	}
 
-	/// Fetches the contents of the specified file
+	/// Creates a new directory on the FTP server.
 	///
-	/// ## Example:
-	/// Connect to localhost then read README.txt
+	/// E## xample:
+	/// Connect to localhost then create the directory cool_directory
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// ftp_conn.mkdir("cool_directory").unwrap(); // Create the directory cool_directory
+	/// println!("{:?}", ftp_conn.list().unwrap()); // Show the files in the current directory
+	/// # // Cleanup
+	/// # ftp_conn.rmdir("cool_directory").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn mkdir(&mut self, dir_name: &str) -> Result<(), FtpError> {
+		self.keep_alive()?;
+
+		let command = format!("MKD {}\r\n", dir_name);
+		self.write_command(command.clone())?;
+
+		let mkd_result = self.wait_for_response()?;
+
+		match mkd_result.status {
+			// Successful action
+			ftp_status::DIRECTORY_CREATED => Ok(()),
+			// Error completing action
+			_ => Err(FtpError::from_status_code(mkd_result, command)),
+		}
+	}
+
+	/// Removees a directory on the FTP server.
+	///
+	/// ## Example:
+	/// Connect to localhost then close the connection.
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # // Create directory to remove
+	/// # ftp_conn.mkdir("test_dir").unwrap();
+	/// println!("{:?}", ftp_conn.list().unwrap()); // Show before
+	/// ftp_conn.rmdir("test_dir").unwrap(); // Remove the directory
+	/// println!("{:?}", ftp_conn.list().unwrap()); // Show after
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn rmdir(&mut self, directory: &str) -> Result<(), FtpError> {
+		self.keep_alive()?;
+
+		let command = format!("RMD {}\r\n", directory);
+		self.write_command(command.clone())?;
+
+		let rmd_result = self.wait_for_response()?;
+		match rmd_result.status {
+			// Successful action
+			ftp_status::FILE_ACTION_COMPLETE => Ok(()),
+			// Error completing action
+			_ => Err(FtpError::from_status_code(rmd_result, command)),
+		}
+	}
+
+	/// Fetches the contents of the specified file
+	///
+	/// The file is transferred in binary (`TYPE I`), so its contents are
+	/// returned exactly as they are stored on the server.
+	///
+	/// ## Example:
+	/// Connect to localhost then read README.txt
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # ftp_conn.write_file("README.txt", "This is the README.txt file. Cool, right?".as_bytes().to_vec()).unwrap();
+	/// println!("{}",
+	///     String::from_utf8_lossy(&ftp_conn.fetch_file("README.txt").unwrap()) // Fetch the contents of the file
+	/// );
+	/// # ftp_conn.rm("README.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn fetch_file(&mut self, file_name: &str) -> Result<Vec<u8>, FtpError> {
+		self.require_transfer_type(TransferType::Image)?;
+
+		let mut reader = self.retr_reader(file_name)?;
+
+		let mut data = vec![];
+		match reader.read_to_end(&mut data) {
+			Ok(_) => (),
+			Err(e) => return Err(FtpError::from_io_error(&e, DatastreamConnectionError)),
+		}
+
+		reader.finish()?;
+		Ok(data)
+	}
+
+	/// Opens the specified file for streaming from the FTP server
+	///
+	/// Unlike `fetch_file`, the contents of the file are not buffered in memory.
+	/// The returned `FtpReader` reads directly from the data connection, making
+	/// it suitable for files too large to hold at once. Once the file has been
+	/// read, `finish` should be called to check the server's final reply.
+	///
+	/// ## Example:
+	/// Connect to localhost then stream README.txt to stdout
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # ftp_conn.write_file("README.txt", "This is the README.txt file. Cool, right?".as_bytes().to_vec()).unwrap();
+	/// let mut reader = ftp_conn.retr_reader("README.txt").unwrap(); // Open the file for reading
+	/// std::io::copy(&mut reader, &mut std::io::stdout()).unwrap();
+	/// reader.finish().unwrap(); // Check that the transfer completed
+	/// # ftp_conn.rm("README.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn retr_reader(&mut self, file_name: &str) -> Result<FtpReader<'_>, FtpError> {
+		self.keep_alive()?;
+
+		let command = format!("RETR {}\r\n", file_name);
+		let (datastream, _) = self.open_transfer(command.clone(), None)?;
+
+		Ok(FtpReader::new(self, datastream, command))
+	}
+
+	/// Opens the specified file for streaming from the FTP server, starting at the given offset
+	///
+	/// Sends `REST` before `RETR`, so the server skips the first `offset` bytes
+	/// of the file. This allows an interrupted download to be continued rather
+	/// than started over. The offset counts bytes as they are sent, so the
+	/// transfer type should be `TransferType::Image`.
+	///
+	/// ## Example:
+	/// Connect to localhost then read README.txt from the 10th byte
+	/// ```rust
+	/// use ftp_lib::{FtpConnection, TransferType};
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # ftp_conn.write_file("README.txt", "This is the README.txt file. Cool, right?".as_bytes().to_vec()).unwrap();
+	/// ftp_conn.transfer_type(TransferType::Image).unwrap();
+	/// let mut reader = ftp_conn.retr_from("README.txt", 10).unwrap(); // Skip the first 10 bytes
+	/// std::io::copy(&mut reader, &mut std::io::stdout()).unwrap();
+	/// reader.finish().unwrap();
+	/// # ftp_conn.rm("README.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn retr_from(&mut self, file_name: &str, offset: u64) -> Result<FtpReader<'_>, FtpError> {
+		self.keep_alive()?;
+
+		let command = format!("RETR {}\r\n", file_name);
+		let (datastream, _) = self.open_transfer(command.clone(), Some(offset))?;
+
+		Ok(FtpReader::new(self, datastream, command))
+	}
+
+	/// Downloads the specified file to a local path, continuing where a previous download stopped
+	///
+	/// The size of the local file is used as the offset to restart the transfer
+	/// from, and the rest of the remote file is appended to it. If the local
+	/// file does not exist yet, the whole file is downloaded, and it is only
+	/// created once the server starts sending. Nothing is transferred when
+	/// the local file is already as large as the remote one. The file is
+	/// transferred in binary (`TYPE I`) so the offsets line up. The number of
+	/// bytes downloaded is returned.
+	///
+	/// ## Example:
+	/// Connect to localhost then download README.txt to a local file
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # ftp_conn.write_file("README.txt", "This is the README.txt file. Cool, right?".as_bytes().to_vec()).unwrap();
+	/// // Running this again after an interruption only fetches what is missing
+	/// ftp_conn.download_resume("README.txt", "README.txt").unwrap();
+	/// # ftp_conn.rm("README.txt").unwrap();
+	/// # std::fs::remove_file("README.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn download_resume<P: AsRef<Path>>(
+		&mut self,
+		file_name: &str,
+		local_path: P,
+	) -> Result<u64, FtpError> {
+		let local_path = local_path.as_ref();
+		let offset = match std::fs::metadata(local_path) {
+			Ok(v) => v.len(),
+			Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+			Err(e) => return Err(LocalFileError(e.to_string())),
+		};
+
+		self.require_transfer_type(TransferType::Image)?;
+
+		if offset > 0 {
+			// Without SIZE there is no way to tell, so the transfer is restarted anyway
+			let remote_size = match self.size(file_name) {
+				Ok(v) => Some(v),
+				Err(SyntaxError(_)) | Err(CommandUnimplemented(_)) => None,
+				Err(e) => return Err(e),
+			};
+			match remote_size {
+				Some(remote_size) if remote_size == offset => return Ok(0),
+				Some(remote_size) if remote_size < offset => {
+					return Err(LocalFileError(format!(
+						"Local file is larger than the remote file ({} > {} bytes)",
+						offset, remote_size
+					)))
+				}
+				_ => (),
+			}
+		}
+
+		let mut reader = if offset > 0 {
+			self.retr_from(file_name, offset)?
+		} else {
+			self.retr_reader(file_name)?
+		};
+
+		// Only created once the server agreed to send the file, so a missing one leaves nothing behind
+		let mut local_file = match OpenOptions::new().create(true).append(true).open(local_path) {
+			Ok(v) => v,
+			Err(e) => {
+				reader.abort()?;
+				return Err(LocalFileError(e.to_string()));
+			}
+		};
+
+		let downloaded = match std::io::copy(&mut reader, &mut local_file) {
+			Ok(v) => v,
+			Err(e) => return Err(FtpError::from_io_error(&e, DatastreamConnectionError)),
+		};
+
+		reader.finish()?;
+		Ok(downloaded)
+	}
+
+	/// Removees a file on the FTP server.
+	///
+	/// The file is transferred in binary (`TYPE I`), so the data is stored
+	/// exactly as it was given.
+	///
+	/// ## Example:
+	/// Writes test.txt
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// ftp_conn.write_file("test.txt", "Cool Data here".as_bytes().to_vec()).unwrap();
+	/// println!("{}",
+	///     String::from_utf8_lossy(&ftp_conn.fetch_file("test.txt").unwrap())
+	/// );
+	/// # // Remove file
+	/// # ftp_conn.rm("test.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn write_file(&mut self, file_name: &str, data: Vec<u8>) -> Result<(), FtpError> {
+		self.require_transfer_type(TransferType::Image)?;
+
+		let mut writer = self.stor_writer(file_name)?;
+
+		match writer.write_all(&data) {
+			Ok(_) => (),
+			Err(e) => return Err(FtpError::from_io_error(&e, ConnectionError)),
+		}
+
+		writer.finish()
+	}
+
+	/// Opens the specified file on the FTP server for streaming writes
+	///
+	/// The returned `FtpWriter` writes directly to the data connection, so
+	/// the contents of the file never need to be held in memory at once.
+	/// Once everything has been written, `finish` should be called to close
+	/// the data connection and check the server's final reply. Dropping the
+	/// writer without calling `finish` aborts the upload.
+	///
+	/// ## Example:
+	/// Writes test.txt piece by piece
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::io::Write;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// let mut writer = ftp_conn.stor_writer("test.txt").unwrap(); // Open the file for writing
+	/// writer.write_all("Cool Data ".as_bytes()).unwrap();
+	/// writer.write_all("here".as_bytes()).unwrap();
+	/// writer.finish().unwrap(); // Check that the transfer completed
+	/// # // Remove file
+	/// # ftp_conn.rm("test.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn stor_writer(&mut self, file_name: &str) -> Result<FtpWriter<'_>, FtpError> {
+		self.keep_alive()?;
+
+		let command = format!("STOR {}\r\n", file_name);
+		let (datastream, _) = self.open_transfer(command.clone(), None)?;
+
+		Ok(FtpWriter::new(self, datastream, command))
+	}
+
+	/// Writes everything from the given reader to the specified file on the FTP server
+	///
+	/// The data is streamed through `stor_writer`, allowing large local files
+	/// to be uploaded without loading them into memory. The number of bytes
+	/// written is returned once the server has confirmed the transfer. If
+	/// reading or writing fails partway, the upload is aborted.
+	///
+	/// ## Example:
+	/// Uploads a local file as upload.txt
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// let mut file = std::fs::File::open("Cargo.toml").unwrap();
+	/// ftp_conn.put_from("upload.txt", &mut file).unwrap(); // Upload the file
+	/// # // Remove file
+	/// # ftp_conn.rm("upload.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn put_from<R: Read>(&mut self, file_name: &str, reader: &mut R) -> Result<u64, FtpError> {
+		let mut writer = self.stor_writer(file_name)?;
+
+		let written = match std::io::copy(reader, &mut writer) {
+			Ok(v) => v,
+			Err(e) => return Err(FtpError::from_io_error(&e, DatastreamConnectionError)),
+		};
+
+		writer.finish()?;
+		Ok(written)
+	}
+
+	/// Appends data to the specified file on the FTP server
+	///
+	/// The file is created if it does not exist yet. Like `write_file`, the
+	/// data is transferred in binary (`TYPE I`).
+	///
+	/// ## Example:
+	/// Appends a line to log.txt
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// ftp_conn.append_file("log.txt", "First line\n".as_bytes().to_vec()).unwrap();
+	/// ftp_conn.append_file("log.txt", "Second line\n".as_bytes().to_vec()).unwrap();
+	/// # // Remove file
+	/// # ftp_conn.rm("log.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn append_file(&mut self, file_name: &str, data: Vec<u8>) -> Result<(), FtpError> {
+		self.require_transfer_type(TransferType::Image)?;
+
+		let mut writer = self.append_writer(file_name)?;
+
+		match writer.write_all(&data) {
+			Ok(_) => (),
+			Err(e) => return Err(FtpError::from_io_error(&e, ConnectionError)),
+		}
+
+		writer.finish()
+	}
+
+	/// Opens the specified file on the FTP server for streaming appends
+	///
+	/// Works like `stor_writer`, except the data is added to the end of the
+	/// file instead of replacing it.
+	///
+	/// ## Example:
+	/// Appends to log.txt piece by piece
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::io::Write;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// let mut writer = ftp_conn.append_writer("log.txt").unwrap(); // Open the file for appending
+	/// writer.write_all("Another ".as_bytes()).unwrap();
+	/// writer.write_all("line\n".as_bytes()).unwrap();
+	/// writer.finish().unwrap(); // Check that the transfer completed
+	/// # // Remove file
+	/// # ftp_conn.rm("log.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn append_writer(&mut self, file_name: &str) -> Result<FtpWriter<'_>, FtpError> {
+		self.keep_alive()?;
+
+		let command = format!("APPE {}\r\n", file_name);
+		let (datastream, _) = self.open_transfer(command.clone(), None)?;
+
+		Ok(FtpWriter::new(self, datastream, command))
+	}
+
+	/// Stores data under a name chosen by the FTP server, returning that name
+	///
+	/// Uses `STOU`, so the file never replaces an existing one. The name is
+	/// taken from the `FILE: name` announced by the server when the transfer
+	/// starts or completes. Like `write_file`, the data is transferred in
+	/// binary (`TYPE I`).
+	///
+	/// ## Example:
+	/// Stores a report without picking a name for it
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// let name = ftp_conn.store_unique("Cool Data here".as_bytes().to_vec()).unwrap();
+	/// println!("Stored as {}", name);
+	/// # // Remove file
+	/// # ftp_conn.rm(&name).unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn store_unique(&mut self, data: Vec<u8>) -> Result<String, FtpError> {
+		self.require_transfer_type(TransferType::Image)?;
+
+		let mut writer = self.stou_writer()?;
+
+		match writer.write_all(&data) {
+			Ok(_) => (),
+			Err(e) => return Err(FtpError::from_io_error(&e, ConnectionError)),
+		}
+
+		// Some servers only give the name once the file is complete
+		let unique_name = writer.unique_name().map(|name| name.to_string());
+		let res = writer.finish_with_reply()?;
+		match unique_name {
+			Some(name) => Ok(name),
+			None => res.parse_unique_name(),
+		}
+	}
+
+	/// Opens a new file with a name chosen by the FTP server for streaming writes
+	///
+	/// Works like `stor_writer`, but sends `STOU` so the file never replaces
+	/// an existing one. The chosen name is available from
+	/// `FtpWriter::unique_name` when the server announces it as the transfer
+	/// starts.
+	///
+	/// ## Example:
+	/// Streams a report without picking a name for it
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::io::Write;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// let mut writer = ftp_conn.stou_writer().unwrap();
+	/// let name = writer.unique_name().unwrap().to_string(); // The name picked by the server
+	/// writer.write_all("Cool Data here".as_bytes()).unwrap();
+	/// writer.finish().unwrap();
+	/// # // Remove file
+	/// # ftp_conn.rm(&name).unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn stou_writer(&mut self) -> Result<FtpWriter<'_>, FtpError> {
+		self.keep_alive()?;
+
+		let command = "STOU\r\n".to_string();
+		let (datastream, res) = self.open_transfer(command.clone(), None)?;
+
+		let unique_name = res.parse_unique_name().ok();
+		Ok(FtpWriter::new(self, datastream, command).with_unique_name(unique_name))
+	}
+
+	/// Uploads a local file to the FTP server, continuing where a previous upload stopped
+	///
+	/// The size of the remote file is queried with `SIZE`, and only the part of
+	/// the local file past that point is sent. The upload is continued with
+	/// `REST` and `STOR` when the server advertises `REST STREAM` in its
+	/// `features`, and with `APPE` otherwise. If the remote file does not
+	/// exist yet, the whole file is uploaded. The file is transferred in binary
+	/// (`TYPE I`) so the offsets line up. The number of bytes uploaded is
+	/// returned.
+	///
+	/// When `set_preserve_modified_time` is enabled, the remote file is given
+	/// the modification time of the local file once the upload is complete.
+	///
+	/// ## Example:
+	/// Connect to localhost then upload Cargo.toml as upload.txt
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// // Running this again after an interruption only sends what is missing
+	/// ftp_conn.upload_resume("Cargo.toml", "upload.txt").unwrap();
+	/// # // Remove file
+	/// # ftp_conn.rm("upload.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn upload_resume<P: AsRef<Path>>(
+		&mut self,
+		local_path: P,
+		file_name: &str,
+	) -> Result<u64, FtpError> {
+		let mut local_file = match File::open(local_path) {
+			Ok(v) => v,
+			Err(e) => return Err(LocalFileError(e.to_string())),
+		};
+		let local_metadata = match local_file.metadata() {
+			Ok(v) => v,
+			Err(e) => return Err(LocalFileError(e.to_string())),
+		};
+		let local_size = local_metadata.len();
+
+		self.require_transfer_type(TransferType::Image)?;
+
+		// A missing remote file means nothing has been uploaded yet
+		let offset = match self.size(file_name) {
+			Ok(v) => v,
+			Err(FileUnavailable) => 0,
+			Err(e) => return Err(e),
+		};
+		if offset > local_size {
+			return Err(LocalFileError(format!(
+				"Local file is smaller than the remote file ({} < {} bytes)",
+				local_size, offset
+			)));
+		}
+		if offset == local_size && offset > 0 {
+			self.preserve_local_modified_time(&local_metadata, file_name)?;
+			return Ok(0);
+		}
+
+		if let Err(e) = local_file.seek(SeekFrom::Start(offset)) {
+			return Err(LocalFileError(e.to_string()));
+		}
+
+		let mut writer = if offset > 0 && self.features()?.rest_stream() {
+			let command = format!("STOR {}\r\n", file_name);
+			match self.open_transfer(command.clone(), Some(offset)) {
+				Ok((datastream, _)) => FtpWriter::new(self, datastream, command),
+				// The server does not know REST for uploads (500, 501, 502 or 504)
+				Err(SyntaxError(_)) | Err(SyntaxErrorParameters(_)) | Err(CommandUnimplemented(_)) => {
+					self.append_writer(file_name)?
+				}
+				Err(e) => return Err(e),
+			}
+		} else if offset > 0 {
+			self.append_writer(file_name)?
+		} else {
+			self.stor_writer(file_name)?
+		};
+
+		// Copied by hand so a failed read of the local file is told apart from a failed upload
+		let mut uploaded = 0;
+		let mut buf = [0; 8192];
+		loop {
+			let read = match local_file.read(&mut buf) {
+				Ok(0) => break,
+				Ok(v) => v,
+				Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+				Err(e) => return Err(LocalFileError(e.to_string())),
+			};
+			if let Err(e) = writer.write_all(&buf[..read]) {
+				return Err(FtpError::from_io_error(&e, DatastreamConnectionError));
+			}
+			uploaded += read as u64;
+		}
+
+		writer.finish()?;
+		self.preserve_local_modified_time(&local_metadata, file_name)?;
+		Ok(uploaded)
+	}
+
+	/// Sets the time the specified file on the FTP server was last modified
+	///
+	/// Uses `MFMT` when the server advertises it in its `features`, and
+	/// otherwise the `MDTM` command with a time before the path, which many
+	/// servers accept for the same purpose. Times are sent in UTC to the
+	/// second.
+	///
+	/// ## Example:
+	/// Connect to localhost then date README.txt back to the start of 2020
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	/// use std::time::{Duration, UNIX_EPOCH};
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # ftp_conn.write_file("README.txt", "This is the README.txt file. Cool, right?".as_bytes().to_vec()).unwrap();
+	/// let time = UNIX_EPOCH + Duration::from_secs(1_577_836_800);
+	/// ftp_conn.set_modified("README.txt", time).unwrap();
+	/// # ftp_conn.rm("README.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn set_modified(&mut self, file_name: &str, time: SystemTime) -> Result<(), FtpError> {
+		self.keep_alive()?;
+
+		let command = if self.features()?.supports("MFMT") {
+			format!("MFMT {} {}\r\n", time::format_time_val(time), file_name)
+		} else {
+			format!("MDTM {} {}\r\n", time::format_time_val(time), file_name)
+		};
+		self.write_command(command.clone())?;
+
+		let result = self.wait_for_response()?;
+		match result.status {
+			// Successful action
+			ftp_status::FILE_STATUS | ftp_status::COMMAND_OKAY | ftp_status::FILE_ACTION_COMPLETE => {
+				Ok(())
+			}
+			// Error completing action
+			_ => Err(FtpError::from_status_code(result, command)),
+		}
+	}
+
+	/// Sets whether uploads of local files keep the local modification time
+	///
+	/// When enabled, `upload_resume` calls `set_modified` with the
+	/// modification time of the local file once the upload is complete. This
+	/// is disabled by default.
+	///
+	/// ## Example:
+	/// Upload Cargo.toml keeping its modification time
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// ftp_conn.set_preserve_modified_time(true);
+	/// ftp_conn.upload_resume("Cargo.toml", "upload.txt").unwrap();
+	/// # // Remove file
+	/// # ftp_conn.rm("upload.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn set_preserve_modified_time(&mut self, preserve_modified_time: bool) {
+		self.preserve_modified_time = preserve_modified_time;
+	}
+
+	/// Gets whether uploads of local files keep the local modification time.
+	pub fn preserve_modified_time(&self) -> bool {
+		self.preserve_modified_time
+	}
+
+	fn preserve_local_modified_time(
+		&mut self,
+		local_metadata: &std::fs::Metadata,
+		file_name: &str,
+	) -> Result<(), FtpError> {
+		if !self.preserve_modified_time {
+			return Ok(());
+		}
+
+		match local_metadata.modified() {
+			Ok(modified) => self.set_modified(file_name, modified),
+			Err(e) => Err(LocalFileError(e.to_string())),
+		}
+	}
+
+	/// Removes a file on the FTP server.
+	///
+	/// ## Example:
+	/// Removes test.txt
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # // Create file to remove
+	/// # ftp_conn.write_file("test.txt", "".as_bytes().to_vec()).unwrap();
+	/// println!("{:?}", ftp_conn.list().unwrap()); // Show before
+	/// ftp_conn.rm("test.txt").unwrap(); // Remove the directory
+	/// println!("{:?}", ftp_conn.list().unwrap()); // Show after
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn rm(&mut self, file: &str) -> Result<(), FtpError> {
+		self.keep_alive()?;
+
+		let command = format!("DELE {}\r\n", file);
+		self.write_command(command.clone())?;
+
+		let dele_result = self.wait_for_response()?;
+		match dele_result.status {
+			// Successful action
+			ftp_status::COMMAND_OKAY | ftp_status::FILE_ACTION_COMPLETE => Ok(()),
+			// Error completing action
+			_ => Err(FtpError::from_status_code(dele_result, command)),
+		}
+	}
+
+	/// Renames the specified file on the FTP server
+	///
+	/// ## Example:
+	/// Connect to localhost rename a new file
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # // Create file
+	/// # ftp_conn.write_file("test.txt", "".as_bytes().to_vec()).unwrap();
+	/// println!("{:?}", ftp_conn.list().unwrap()); // Display begining file structure
+	/// ftp_conn.rename("test.txt", "cool.txt"); // Rename from test.txt to cool.txt
+	/// println!("{:?}", ftp_conn.list().unwrap()); // Verify the change
+	/// # // Cleanup
+	/// # ftp_conn.rm("cool.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn rename(&mut self, src: &str, dst: &str) -> Result<(), FtpError> {
+		self.keep_alive()?;
+
+		let command = format!("RNFR {}\r\n", src);
+		self.write_command(command.clone())?;
+
+		let rnfr_result = self.wait_for_response()?;
+		match rnfr_result.status {
+			// Successful action
+			ftp_status::FILE_ACTION_COMPLETE | ftp_status::FILE_NEED_INFORMATION => {
+				let command = format!("RNTO {}\r\n", dst);
+				self.write_command(command.clone())?;
+
+				let rnto_result = self.wait_for_response()?;
+
+				match rnto_result.status {
+					// Successful action
+					ftp_status::FILE_ACTION_COMPLETE => Ok(()),
+					// Error completing action
+					_ => Err(FtpError::from_status_code(rnto_result, command)),
+				}
+			}
+			// Error completing action
+			_ => Err(FtpError::from_status_code(rnfr_result, command)),
+		}
+	}
+
+	/// Gets the size in bytes of the specified file on the FTP server
+	///
+	/// Uses the `SIZE` command from RFC 3659. The size reported depends on
+	/// the transfer type, so set `TransferType::Image` first to get the exact
+	/// number of bytes stored.
+	///
+	/// ## Example:
+	/// Connect to localhost then get the size of README.txt
+	/// ```rust
+	/// use ftp_lib::{FtpConnection, TransferType};
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # ftp_conn.write_file("README.txt", "This is the README.txt file. Cool, right?".as_bytes().to_vec()).unwrap();
+	/// ftp_conn.transfer_type(TransferType::Image).unwrap();
+	/// println!("{}", ftp_conn.size("README.txt").unwrap()); // Size in bytes
+	/// # ftp_conn.rm("README.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn size(&mut self, file_name: &str) -> Result<u64, FtpError> {
+		self.keep_alive()?;
+
+		let command = format!("SIZE {}\r\n", file_name);
+		self.write_command(command.clone())?;
+
+		let size_result = self.wait_for_response()?;
+		match size_result.status {
+			// Successful action
+			ftp_status::FILE_STATUS => match size_result.message().trim().parse() {
+				Ok(v) => Ok(v),
+				Err(_) => Err(InvalidResponseFormatError),
+			},
+			// Error completing action
+			_ => Err(FtpError::from_status_code(size_result, command)),
+		}
+	}
+
+	/// Gets the time the specified file on the FTP server was last modified
+	///
+	/// Uses the `MDTM` command from RFC 3659. The server gives the time in
+	/// UTC, including fractions of a second when it knows them.
+	///
+	/// ## Example:
+	/// Connect to localhost then get the modification time of README.txt
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # ftp_conn.write_file("README.txt", "This is the README.txt file. Cool, right?".as_bytes().to_vec()).unwrap();
+	/// println!("{:?}", ftp_conn.modified("README.txt").unwrap()); // Time of the last change
+	/// # ftp_conn.rm("README.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn modified(&mut self, file_name: &str) -> Result<SystemTime, FtpError> {
+		self.keep_alive()?;
+
+		let command = format!("MDTM {}\r\n", file_name);
+		self.write_command(command.clone())?;
+
+		let mdtm_result = self.wait_for_response()?;
+		match mdtm_result.status {
+			// Successful action
+			ftp_status::FILE_STATUS => match time::parse_time_val(mdtm_result.message().trim()) {
+				Some(v) => Ok(v),
+				None => Err(InvalidResponseFormatError),
+			},
+			// Error completing action
+			_ => Err(FtpError::from_status_code(mdtm_result, command)),
+		}
+	}
+
+	/// Gets the details of a single file or directory on the FTP server
+	///
+	/// `MLST` is used when the server advertises it in its `features`, as it
+	/// gives every detail at once. Otherwise the size and modification time
+	/// are asked for with `SIZE` and `MDTM`, and when those don't work on the
+	/// path, such as for directories, the path is looked up in a `LIST` of
+	/// its parent directory.
+	/// Without `MLST`, the transfer type is switched to `TransferType::Image`
+	/// so `SIZE` gives the exact number of bytes.
+	///
+	/// ## Example:
+	/// Connect to localhost then show the details of README.txt
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # ftp_conn.write_file("README.txt", "This is the README.txt file. Cool, right?".as_bytes().to_vec()).unwrap();
+	/// let metadata = ftp_conn.stat("README.txt").unwrap();
+	/// println!("{:?} bytes, modified {:?}", metadata.size(), metadata.modified());
+	/// # ftp_conn.rm("README.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn stat(&mut self, path: &str) -> Result<filestructure::Metadata, FtpError> {
+		self.keep_alive()?;
+
+		if self.features()?.supports("MLST") {
+			return Ok(self.mlst(Some(path))?.into());
+		}
+
+		// SIZE only works on files, so anything else is left for LIST
+		self.require_transfer_type(TransferType::Image)?;
+		match self.size(path) {
+			Ok(size) => {
+				let modified = match self.modified(path) {
+					Ok(v) => Some(v),
+					Err(SyntaxError(_)) | Err(CommandUnimplemented(_)) => None,
+					Err(e) => return Err(e),
+				};
+				return Ok(filestructure::Metadata::new(
+					Some(filestructure::DirectoryItemType::File),
+					Some(size),
+					modified,
+				));
+			}
+			Err(FileUnavailable)
+			| Err(SyntaxError(_))
+			| Err(SyntaxErrorParameters(_))
+			| Err(CommandUnimplemented(_)) => (),
+			Err(e) => return Err(e),
+		}
+
+		// Listing a directory shows what it contains, so the path is looked for in its parent
+		let name = path.trim_end_matches('/');
+		let (parent, base_name) = match name.rfind('/') {
+			Some(0) => (Some("/"), &name[1..]),
+			Some(split) => (Some(&name[..split]), &name[split + 1..]),
+			None => (None, name),
+		};
+		if base_name.is_empty() || base_name == "." || base_name == ".." {
+			// The root and the relative directory names are never listed in a parent
+			let command = format!("LIST {}\r\n", path);
+			self.read_datastream(command)?;
+			return Ok(filestructure::Metadata::new(
+				Some(filestructure::DirectoryItemType::Directory),
+				None,
+				None,
+			));
+		}
+
+		let command = match parent {
+			Some(parent) => format!("LIST {}\r\n", parent),
+			None => "LIST\r\n".to_string(),
+		};
+		let (datavec, _) = self.read_datastream(command)?;
+		let items = self
+			.list_parser
+			.parse_listing(&String::from_utf8_lossy(&datavec))?;
+
+		match items.into_iter().find(|item| item.name() == base_name) {
+			Some(item) => Ok(item.into()),
+			None => Err(FileUnavailable),
+		}
+	}
+
+	/// Sends a `SITE` command to the FTP server
+	///
+	/// `SITE` gives access to commands specific to the server, so the command
+	/// is sent as given and the full reply is returned for the caller to
+	/// interpret, whatever its status. Only failing to talk to the server is
+	/// an `FtpError`. The `chmod`, `umask`, `idle` and `utime` helpers check
+	/// the reply themselves.
+	///
+	/// ## Example:
+	/// Connect to localhost then ask the server for its `SITE` commands
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// println!("{}", ftp_conn.site("HELP").unwrap().content);
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn site(&mut self, command: &str) -> Result<FtpResponse, FtpError> {
+		self.keep_alive()?;
+
+		let command = format!("SITE {}\r\n", command);
+		self.write_command(command.clone())?;
+
+		self.wait_for_response()
+	}
+
+	/// Changes the permissions of the specified file on the FTP server
+	///
+	/// Uses `SITE CHMOD`, which takes the mode as Unix permission bits, such
+	/// as `0o755`.
+	///
+	/// ## Example:
+	/// Connect to localhost then make run.sh executable
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # ftp_conn.write_file("run.sh", "echo Hello".as_bytes().to_vec()).unwrap();
+	/// ftp_conn.chmod("run.sh", 0o755).unwrap();
+	/// # ftp_conn.rm("run.sh").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn chmod(&mut self, file_name: &str, mode: u32) -> Result<(), FtpError> {
+		self.site_action(&format!("CHMOD {:03o} {}", mode, file_name))
+	}
+
+	/// Sets the permission bits removed from files created on the FTP server
+	///
+	/// Uses `SITE UMASK`, which takes the mask as Unix permission bits, such
+	/// as `0o022`.
+	///
+	/// ## Example:
+	/// Connect to localhost then keep new files private
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// ftp_conn.umask(0o077).unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn umask(&mut self, mask: u32) -> Result<(), FtpError> {
+		self.site_action(&format!("UMASK {:03o}", mask))
+	}
+
+	/// Sets how long the FTP server waits for a command before closing the connection
+	///
+	/// Uses `SITE IDLE`. Servers usually cap the time at a configured maximum.
+	///
+	/// ## Example:
+	/// Connect to localhost then allow 10 minutes between commands
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// ftp_conn.idle(600).unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn idle(&mut self, seconds: u64) -> Result<(), FtpError> {
+		self.site_action(&format!("IDLE {}", seconds))
+	}
+
+	/// Sets the access, modification and change times of the specified file on the FTP server
+	///
+	/// Uses `SITE UTIME` with all three times in UTC, as supported by ProFTPD
+	/// and Pure-FTPd. To only set the modification time, `set_modified` works
+	/// with more servers.
+	///
+	/// ## Example:
+	/// Connect to localhost then date README.txt back to the start of 2020
+	/// ```rust
+	/// use ftp_lib::FtpConnection;
+	/// use std::net::SocketAddrV4;
+	/// use std::time::{Duration, UNIX_EPOCH};
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// # ftp_conn.write_file("README.txt", "This is the README.txt file. Cool, right?".as_bytes().to_vec()).unwrap();
+	/// let time = UNIX_EPOCH + Duration::from_secs(1_577_836_800);
+	/// ftp_conn.utime("README.txt", time, time, time).unwrap();
+	/// # ftp_conn.rm("README.txt").unwrap();
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn utime(
+		&mut self,
+		file_name: &str,
+		accessed: SystemTime,
+		modified: SystemTime,
+		changed: SystemTime,
+	) -> Result<(), FtpError> {
+		self.site_action(&format!(
+			"UTIME {} {} {} {} UTC",
+			file_name,
+			time::format_time_val(accessed),
+			time::format_time_val(modified),
+			time::format_time_val(changed)
+		))
+	}
+
+	/// Gets the operating system of the FTP server
+	///
+	/// ## Example:
+	/// Connect to localhost then pick a `LIST` parser for the server
+	/// ```rust
+	/// use ftp_lib::{FtpConnection, SystemType};
+	/// use ftp_lib::listparser::{DosParser, VmsParser};
+	/// use std::net::SocketAddrV4;
+	///
+	/// let mut ftp_conn = FtpConnection::connect(
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
+	/// ).unwrap();
+	///
+	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	///
+	/// match ftp_conn.system().unwrap() {
+	///     SystemType::Windows => ftp_conn.set_list_parser(DosParser),
+	///     SystemType::Vms => ftp_conn.set_list_parser(VmsParser),
+	///     _ => (),
+	/// }
+	///
+	/// ftp_conn.quit().unwrap();
+	/// ```
+	pub fn system(&mut self) -> Result<SystemType, FtpError> {
+		self.keep_alive()?;
+
+		let command = "SYST\r\n".to_string();
+		self.write_command(command.clone())?;
+
+		let syst_result = self.wait_for_response()?;
+		match syst_result.status {
+			// Successful action
+			ftp_status::SYSTEM_TYPE => syst_result.parse_system_type(),
+			// Error completing action
+			_ => Err(FtpError::from_status_code(syst_result, command)),
+		}
+	}
+
+	/// Gets the status of the FTP server and of the connection
+	///
+	/// The text of the `STAT` reply is returned as sent, as its contents differ
+	/// between servers.
+	///
+	/// ## Example:
+	/// Connect to localhost then show the status of the connection
 	/// ```rust
 	/// use ftp_lib::FtpConnection;
 	/// use std::net::SocketAddrV4;
 	///
 	/// let mut ftp_conn = FtpConnection::connect(
-	///     "127.0.0.1:21".parse().unwrap()
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 	/// ).unwrap();
 	///
 	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
 	///
-	/// # ftp_conn.write_file("README.txt", "This is the README.txt file. Cool, right?".as_bytes().to_vec()).unwrap();
-	/// println!("{}",
-	///     String::from_utf8_lossy(&ftp_conn.fetch_file("README.txt").unwrap()) // Fetch the contents of the file
-	/// );
-	/// # ftp_conn.rm("README.txt").unwrap();
+	/// println!("{}", ftp_conn.status().unwrap());
 	///
 	/// ftp_conn.quit().unwrap();
 	/// ```
-	pub fn fetch_file(&mut self, file_name: &str) -> Result<Vec<u8>, FtpError> {
-		let datastream_addr = self.pasv()?;
+	pub fn status(&mut self) -> Result<String, FtpError> {
+		self.keep_alive()?;
 
-		let command = format!("RETR {}\r\n", file_name);
+		let command = "STAT\r\n".to_string();
 		self.write_command(command.clone())?;
 
-		let data = self.connect_datastream(datastream_addr)?;
-
-		// NOTE: The following statuses do not cover all cases.
-		// I am not sure where status can be returned for now.
-		let res = self.wait_for_response()?;
-		match res.status {
-			// Successful action
-			ftp_status::FILE_OPENING_DATA | ftp_status::DATA_TRANSFER_STARTING => (),
-			// Error completing action
-			_ => return Err(FtpError::from_status_code(res, command)),
-		}
-
-		let res = self.wait_for_response()?;
-		match res.status {
+		let stat_result = self.wait_for_response()?;
+		match stat_result.status {
 			// Successful action
-			ftp_status::DATA_CLOSING | ftp_status::FILE_ACTION_COMPLETE => (),
+			ftp_status::SYSTEM_STATUS => Ok(stat_result.message()),
 			// Error completing action
-			_ => return Err(FtpError::from_status_code(res, command)),
+			_ => Err(FtpError::from_status_code(stat_result, command)),
 		}
-
-		Ok(data)
 	}
 
-	/// Removees a file on the FTP server.
+	/// Lists the specified file or directory over the control connection
+	///
+	/// `STAT` with a path sends the same listing as `LIST` in its reply, so no
+	/// data connection is needed. The listing is read with the parser set by
+	/// `set_list_parser`. Some servers answer a missing path with a single
+	/// line instead of an error, which gives `FtpError::FileUnavailable`.
 	///
 	/// ## Example:
-	/// Writes test.txt
+	/// Connect to localhost then list the current directory
 	/// ```rust
 	/// use ftp_lib::FtpConnection;
 	/// use std::net::SocketAddrV4;
 	///
 	/// let mut ftp_conn = FtpConnection::connect(
-	///     "127.0.0.1:21".parse().unwrap()
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 	/// ).unwrap();
 	///
 	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
 	///
-	/// ftp_conn.write_file("test.txt", "Cool Data here".as_bytes().to_vec()).unwrap();
-	/// println!("{}",
-	///     String::from_utf8_lossy(&ftp_conn.fetch_file("test.txt").unwrap())
-	/// );
-	/// # // Remove file
-	/// # ftp_conn.rm("test.txt").unwrap();
+	/// println!("{:?}", ftp_conn.status_of(".").unwrap());
 	///
 	/// ftp_conn.quit().unwrap();
 	/// ```
-	pub fn write_file(&mut self, file_name: &str, data: Vec<u8>) -> Result<(), FtpError> {
-		let datastream_addr = self.pasv()?;
+	pub fn status_of(&mut self, path: &str) -> Result<Vec<filestructure::DirectoryItem>, FtpError> {
+		self.keep_alive()?;
 
-		let command = format!("STOR {}\r\n", file_name);
+		let command = format!("STAT {}\r\n", path);
 		self.write_command(command.clone())?;
 
-		if let Ok(mut datastream) = TcpStream::connect(datastream_addr) {
-			let data_write_res = datastream.write_all(&data);
-			match data_write_res {
-				Ok(_) => (),
-				Err(_) => return Err(ConnectionError),
-			}
-
-			// I have absolutely no idea what the following could return
-			match datastream.shutdown(std::net::Shutdown::Both) {
-				Ok(_) => (),
-				Err(_) => return Err(DatastreamConnectionError),
-			}
-
-			let res = self.wait_for_response()?;
-			match res.status {
-				// Sucessful action
-				ftp_status::FILE_OPENING_DATA | ftp_status::DATA_TRANSFER_STARTING => (),
-				// Error completing action
-				_ => return Err(FtpError::from_status_code(res, command)),
-			}
+		let stat_result = self.wait_for_response()?;
+		match stat_result.status {
+			// Successful action
+			ftp_status::SYSTEM_STATUS | ftp_status::DIRECTORY_STATUS | ftp_status::FILE_STATUS => {
+				// A single line has no room for a listing, only for a message such as "No such file"
+				let lines = stat_result.lines();
+				if lines.len() < 2 {
+					return Err(FileUnavailable);
+				}
 
-			let res = self.wait_for_response()?;
-			match res.status {
-				// Successful action
-				ftp_status::DATA_CLOSING | ftp_status::FILE_ACTION_COMPLETE => (),
-				// Error completing action
-				_ => return Err(FtpError::from_status_code(res, command)),
+				// The listing is between the first and last lines, usually indented by a space
+				let status_prefix = format!("{}-", stat_result.status);
+				let listing: Vec<&str> = lines
+					.iter()
+					.skip(1)
+					.take(lines.len() - 2)
+					.map(|line| line.strip_prefix(status_prefix.as_str()).unwrap_or(line))
+					.map(|line| line.strip_prefix(' ').unwrap_or(line))
+					.collect();
+
+				self.list_parser.parse_listing(&listing.join("\n"))
 			}
-			Ok(())
-		} else {
-			Err(ConnectionError)
+			// Error completing action
+			_ => Err(FtpError::from_status_code(stat_result, command)),
 		}
 	}
 
-	/// Removes a file on the FTP server.
+	/// Gets the help text of the FTP server
+	///
+	/// Without a topic, servers usually list the commands they recognize. With
+	/// a topic, such as the name of a command, the help for it is given.
 	///
 	/// ## Example:
-	/// Removes test.txt
+	/// Connect to localhost then show the commands the server knows
 	/// ```rust
 	/// use ftp_lib::FtpConnection;
 	/// use std::net::SocketAddrV4;
 	///
 	/// let mut ftp_conn = FtpConnection::connect(
-	///     "127.0.0.1:21".parse().unwrap()
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 	/// ).unwrap();
 	///
 	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
 	///
-	/// # // Create file to remove
-	/// # ftp_conn.write_file("test.txt", "".as_bytes().to_vec()).unwrap();
-	/// println!("{:?}", ftp_conn.list().unwrap()); // Show before
-	/// ftp_conn.rm("test.txt").unwrap(); // Remove the directory
-	/// println!("{:?}", ftp_conn.list().unwrap()); // Show after
+	/// println!("{}", ftp_conn.help(None).unwrap());
+	/// println!("{}", ftp_conn.help(Some("STOR")).unwrap());
 	///
 	/// ftp_conn.quit().unwrap();
 	/// ```
-	pub fn rm(&mut self, file: &str) -> Result<(), FtpError> {
-		let command = format!("DELE {}\r\n", file);
+	pub fn help(&mut self, topic: Option<&str>) -> Result<String, FtpError> {
+		self.keep_alive()?;
+
+		let command = match topic {
+			Some(topic) => format!("HELP {}\r\n", topic),
+			None => "HELP\r\n".to_string(),
+		};
 		self.write_command(command.clone())?;
 
-		let dele_result = self.wait_for_response()?;
-		match dele_result.status {
+		let help_result = self.wait_for_response()?;
+		match help_result.status {
 			// Successful action
-			ftp_status::COMMAND_OKAY | ftp_status::FILE_ACTION_COMPLETE => Ok(()),
+			ftp_status::HELP_MESSAGE | ftp_status::SYSTEM_STATUS => Ok(help_result.message()),
 			// Error completing action
-			_ => Err(FtpError::from_status_code(dele_result, command)),
+			_ => Err(FtpError::from_status_code(help_result, command)),
 		}
 	}
 
-	/// Renames the specified file on the FTP server
+	/// Checks the FTP server is still responding, without doing anything else
 	///
 	/// ## Example:
-	/// Connect to localhost rename a new file
+	/// Connect to localhost then make sure the server is still there
 	/// ```rust
 	/// use ftp_lib::FtpConnection;
 	/// use std::net::SocketAddrV4;
 	///
 	/// let mut ftp_conn = FtpConnection::connect(
-	///     "127.0.0.1:21".parse().unwrap()
+	///     "127.0.0.1:21".parse::<SocketAddrV4>().unwrap()
 	/// ).unwrap();
 	///
 	/// ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
 	///
-	/// # // Create file
-	/// # ftp_conn.write_file("test.txt", "".as_bytes().to_vec()).unwrap();
-	/// println!("{:?}", ftp_conn.list().unwrap()); // Display begining file structure
-	/// ftp_conn.rename("test.txt", "cool.txt"); // Rename from test.txt to cool.txt
-	/// println!("{:?}", ftp_conn.list().unwrap()); // Verify the change
-	/// # // Cleanup
-	/// # ftp_conn.rm("cool.txt").unwrap();
+	/// ftp_conn.noop().unwrap();
 	///
 	/// ftp_conn.quit().unwrap();
 	/// ```
-	pub fn rename(&mut self, src: &str, dst: &str) -> Result<(), FtpError> {
-		let command = format!("RNFR {}\r\n", src);
+	pub fn noop(&mut self) -> Result<(), FtpError> {
+		let command = "NOOP\r\n".to_string();
 		self.write_command(command.clone())?;
 
-		let rnfr_result = self.wait_for_response()?;
-		match rnfr_result.status {
+		let noop_result = self.wait_for_response()?;
+		match noop_result.status {
 			// Successful action
-			ftp_status::FILE_ACTION_COMPLETE | ftp_status::FILE_NEED_INFORMATION => {
-				let command = format!("RNTO {}\r\n", dst);
-				self.write_command(command.clone())?;
+			ftp_status::COMMAND_OKAY => Ok(()),
+			// Error completing action
+			_ => Err(FtpError::from_status_code(noop_result, command)),
+		}
+	}
 
-				let rnto_result = self.wait_for_response()?;
+	fn require_transfer_type(&mut self, transfer_type: TransferType) -> Result<(), FtpError> {
+		// Only send TYPE when the type is not already known to be set
+		if self.transfer_type == Some(transfer_type) {
+			Ok(())
+		} else {
+			self.transfer_type(transfer_type)
+		}
+	}
 
-				match rnto_result.status {
-					// Successful action
-					ftp_status::FILE_ACTION_COMPLETE => Ok(()),
-					// Error completing action
-					_ => Err(FtpError::from_status_code(rnto_result, command)),
-				}
-			}
+	/// Sends a `SITE` command which only reports whether it worked.
+	fn site_action(&mut self, command: &str) -> Result<(), FtpError> {
+		let site_result = self.site(command)?;
+		match site_result.status {
+			// Successful action
+			ftp_status::COMMAND_OKAY => Ok(()),
 			// Error completing action
-			_ => Err(FtpError::from_status_code(rnfr_result, command)),
+			_ => Err(FtpError::from_status_code(
+				site_result,
+				format!("SITE {}\r\n", command),
+			)),
+		}
+	}
+
+	fn read_datastream(&mut self, command: String) -> Result<(Vec<u8>, FtpResponse), FtpError> {
+		let (mut datastream, _) = self.open_transfer(command.clone(), None)?;
+
+		// Read from then kill the connection to the datastream
+		let mut datavec = vec![];
+		let read = datastream.read_to_end(&mut datavec);
+		let _ = datastream.shutdown();
+
+		let res = self.finish_transfer(command)?;
+		match read {
+			Ok(_) => Ok((datavec, res)),
+			Err(e) => Err(FtpError::from_io_error(&e, DatastreamConnectionError)),
 		}
 	}
 
-	fn connect_datastream(&self, datastream_addr: SocketAddrV4) -> Result<Vec<u8>, FtpError> {
-		// Connect to the datastream on the specified port
-		match TcpStream::connect(datastream_addr) {
-			Ok(mut datastream) => {
-				// Read from then kill the connection to the datastream
-				let mut datavec = vec![];
-				match datastream.read_to_end(&mut datavec) {
-					Ok(_) => (),
-					Err(_) => return Err(DatastreamConnectionError),
+	fn open_transfer(
+		&mut self,
+		command: String,
+		restart_offset: Option<u64>,
+	) -> Result<(FtpStream, FtpResponse), FtpError> {
+		let pending = self.prepare_datastream()?;
+
+		// REST has to come right before the transfer command it applies to
+		if let Some(offset) = restart_offset {
+			self.rest(offset)?;
+		}
+
+		self.write_command(command.clone())?;
+
+		let (datastream, res) = match pending {
+			PendingDatastream::Connect(datastream_addr) => {
+				// The server is already listening, so connect before waiting for its reply
+				let connected = match self.timeouts.connect {
+					Some(timeout) => TcpStream::connect_timeout(&datastream_addr, timeout),
+					None => TcpStream::connect(datastream_addr),
+				};
+				let datastream = match connected {
+					Ok(v) => v,
+					Err(e) => {
+						self.discard_transfer_replies(false);
+						return Err(FtpError::from_io_error(&e, DatastreamConnectionError));
+					}
+				};
+
+				(datastream, self.wait_for_transfer_start(command)?)
+			}
+			PendingDatastream::Listen(listener) => {
+				// The server only connects once it has accepted the command
+				let res = self.wait_for_transfer_start(command)?;
+				match self.accept_datastream(listener) {
+					Ok(datastream) => (datastream, res),
+					Err(e) => {
+						self.discard_transfer_replies(true);
+						return Err(e);
+					}
 				}
+			}
+		};
+
+		// Servers only start the TLS handshake once they have accepted the command
+		match self.wrap_datastream(datastream) {
+			Ok(datastream) => Ok((datastream, res)),
+			Err(e) => {
+				self.discard_transfer_replies(true);
+				Err(e)
+			}
+		}
+	}
+
+	/// Reads the replies a transfer command still gets after failing on the client's side.
+	///
+	/// Left unread, they would answer the next command instead. `started` is
+	/// set when the 1xx reply starting the transfer was already read.
+	fn discard_transfer_replies(&mut self, started: bool) {
+		match self.wait_for_response() {
+			Ok(ref res) if !started && res.status < 200 => {
+				let _ = self.wait_for_response();
+			}
+			_ => (),
+		}
+	}
 
-				match datastream.shutdown(std::net::Shutdown::Both) {
-					Ok(_) => (),
-					Err(_) => return Err(DatastreamConnectionError),
+	fn accept_datastream(&self, listener: TcpListener) -> Result<TcpStream, FtpError> {
+		let server_ip = match self.reader.get_ref().get_ref().peer_addr() {
+			Ok(addr) => addr.ip(),
+			Err(_) => return Err(ConnectionError),
+		};
+
+		// TcpListener has no accept timeout, so poll it until the deadline passes
+		let deadline = self.timeouts.connect.map(|timeout| Instant::now() + timeout);
+		if deadline.is_some() && listener.set_nonblocking(true).is_err() {
+			return Err(DatastreamConnectionError);
+		}
+		loop {
+			match listener.accept() {
+				// Only the server may connect, anyone else could be injecting data into the transfer
+				Ok((datastream, addr)) if addr.ip() != server_ip => drop(datastream),
+				Ok((datastream, _)) => {
+					return match datastream.set_nonblocking(false) {
+						Ok(_) => Ok(datastream),
+						Err(_) => Err(DatastreamConnectionError),
+					}
+				}
+				Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+					if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+						return Err(Timeout);
+					}
+					std::thread::sleep(Duration::from_millis(10));
 				}
+				Err(_) => return Err(DatastreamConnectionError),
+			}
+		}
+	}
+
+	fn wrap_datastream(&self, datastream: TcpStream) -> Result<FtpStream, FtpError> {
+		match datastream
+			.set_read_timeout(self.timeouts.data)
+			.and_then(|_| datastream.set_write_timeout(self.timeouts.data))
+		{
+			Ok(_) => (),
+			Err(_) => return Err(DatastreamConnectionError),
+		}
 
-				Ok(datavec)
+		// Data connections are protected whenever the control connection is
+		#[cfg(feature = "tls")]
+		{
+			if let Some(ref tls_config) = self.tls_config {
+				return tls_config.wrap(datastream);
 			}
-			Err(_) => Err(DatastreamConnectionError),
+		}
+
+		Ok(FtpStream::Plain(datastream))
+	}
+
+	fn rest(&mut self, offset: u64) -> Result<(), FtpError> {
+		let command = format!("REST {}\r\n", offset);
+		self.write_command(command.clone())?;
+
+		let rest_result = self.wait_for_response()?;
+		match rest_result.status {
+			// Successful action
+			ftp_status::FILE_NEED_INFORMATION => Ok(()),
+			// Error completing action
+			_ => Err(FtpError::from_status_code(rest_result, command)),
+		}
+	}
+
+	fn wait_for_transfer_start(&mut self, command: String) -> Result<FtpResponse, FtpError> {
+		let res = self.wait_for_response()?;
+		match res.status {
+			// Successful action
+			ftp_status::FILE_OPENING_DATA | ftp_status::DATA_TRANSFER_STARTING => Ok(res),
+			// Error completing action
+			_ => Err(FtpError::from_status_code(res, command)),
+		}
+	}
+
+	fn finish_transfer(&mut self, command: String) -> Result<FtpResponse, FtpError> {
+		let res = self.wait_for_response()?;
+		match res.status {
+			// Successful action
+			ftp_status::DATA_CLOSING | ftp_status::FILE_ACTION_COMPLETE => Ok(res),
+			// Error completing action
+			_ => Err(FtpError::from_status_code(res, command)),
+		}
+	}
+
+	pub(crate) fn abort_transfer(
+		&mut self,
+		mut datastream: FtpStream,
+		command: String,
+	) -> Result<(), FtpError> {
+		self.send_telnet_synch()?;
+
+		let abor_command = "ABOR\r\n".to_string();
+		self.write_command(abor_command.clone())?;
+
+		// Closing the data connection unblocks servers still waiting to send or receive
+		let _ = datastream.shutdown();
+		drop(datastream);
+
+		let res = self.wait_for_response()?;
+		match res.status {
+			// The transfer was interrupted, and the reply to ABOR follows
+			ftp_status::DATA_CLOSED_ABORTING | ftp_status::ACTION_ABORTED_PROCESSING => {
+				self.finish_abort(abor_command)
+			}
+			// The transfer finished before the server saw ABOR, which still gets its own reply
+			ftp_status::DATA_CLOSING | ftp_status::FILE_ACTION_COMPLETE => {
+				self.finish_abort(abor_command)
+			}
+			// There was nothing left to abort
+			ftp_status::DATA_NOT_TRANSFERING => Ok(()),
+			// Error completing action
+			_ => Err(FtpError::from_status_code(res, command)),
+		}
+	}
+
+	fn finish_abort(&mut self, command: String) -> Result<(), FtpError> {
+		let res = self.wait_for_response()?;
+		match res.status {
+			// Successful action
+			ftp_status::DATA_CLOSING | ftp_status::DATA_NOT_TRANSFERING => Ok(()),
+			// Error completing action
+			_ => Err(FtpError::from_status_code(res, command)),
+		}
+	}
+
+	fn send_telnet_synch(&mut self) -> Result<(), FtpError> {
+		const IAC: u8 = 255;
+		const IP: u8 = 244;
+		const DM: u8 = 242;
+
+		// Telnet commands cannot be sent inside TLS records, so ABOR is sent on its own there
+		let stream = match self.reader.get_mut().plain_mut() {
+			Some(stream) => stream,
+			None => return Ok(()),
+		};
+
+		// IP interrupts the transfer, and the urgent data makes the server look at the
+		// control connection even while it is busy. The Synch ends with DM (RFC 854)
+		let sent = SockRef::from(&*stream)
+			.send_out_of_band(&[IAC, IP, IAC])
+			.and_then(|_| stream.write_all(&[DM]));
+		match sent {
+			Ok(_) => Ok(()),
+			Err(e) => Err(FtpError::from_io_error(&e, ConnectionError)),
+		}
+	}
+
+	fn prepare_datastream(&mut self) -> Result<PendingDatastream, FtpError> {
+		match self.data_mode {
+			// PASV cannot describe an IPv6 address, so EPSV is always needed there
+			DataMode::Passive if self.is_ipv6()? => Ok(PendingDatastream::Connect(self.epsv()?)),
+			DataMode::Passive => Ok(PendingDatastream::Connect(SocketAddr::V4(self.pasv()?))),
+			DataMode::ExtendedPassive => match self.epsv() {
+				Ok(datastream_addr) => Ok(PendingDatastream::Connect(datastream_addr)),
+				// The server does not know EPSV (500 or 502), so stop trying it
+				Err(SyntaxError(_)) | Err(CommandUnimplemented(_)) if !self.is_ipv6()? => {
+					self.data_mode = DataMode::Passive;
+					Ok(PendingDatastream::Connect(SocketAddr::V4(self.pasv()?)))
+				}
+				Err(e) => Err(e),
+			},
+			// PORT cannot describe an IPv6 address either, so EPRT is needed there
+			DataMode::Active if self.is_ipv6()? => self.eprt(),
+			DataMode::Active => self.port(),
+			DataMode::ExtendedActive => self.eprt(),
+		}
+	}
+
+	fn is_ipv6(&self) -> Result<bool, FtpError> {
+		match self.reader.get_ref().get_ref().peer_addr() {
+			Ok(addr) => Ok(addr.is_ipv6()),
+			Err(_) => Err(ConnectionError),
 		}
 	}
 
@@ -881,35 +3285,191 @@ impl FtpConnection {
 		}
 	}
 
+	fn epsv(&mut self) -> Result<SocketAddr, FtpError> {
+		// EPSV only gives the port, the host is the same as the control connection
+		let command = "EPSV\r\n".to_string();
+		self.write_command(command.clone())?;
+
+		let epsv_result = self.wait_for_response()?;
+
+		match epsv_result.status {
+			// Successful action
+			ftp_status::ENTERING_EXTENDED_PASSIVE => {
+				let port = epsv_result.parse_epsv_port()?;
+				match self.reader.get_ref().get_ref().peer_addr() {
+					Ok(addr) => Ok(SocketAddr::new(addr.ip(), port)),
+					Err(_) => Err(ConnectionError),
+				}
+			}
+			// Error completing action
+			_ => Err(FtpError::from_status_code(epsv_result, command)),
+		}
+	}
+
+	fn port(&mut self) -> Result<PendingDatastream, FtpError> {
+		let listener = self.bind_datastream_listener()?;
+		let local_addr = match listener.local_addr() {
+			Ok(v) => v,
+			Err(_) => return Err(DatastreamConnectionError),
+		};
+
+		// The listener shares the address family of the control connection, which is IPv4 here
+		let ip = match local_addr {
+			SocketAddr::V4(addr) => addr.ip().octets(),
+			SocketAddr::V6(_) => return Err(DatastreamConnectionError),
+		};
+
+		let command = format!(
+			"PORT {},{},{},{},{},{}\r\n",
+			ip[0],
+			ip[1],
+			ip[2],
+			ip[3],
+			local_addr.port() / 256,
+			local_addr.port() % 256
+		);
+		self.write_command(command.clone())?;
+
+		let port_result = self.wait_for_response()?;
+		match port_result.status {
+			// Successful action
+			ftp_status::COMMAND_OKAY => Ok(PendingDatastream::Listen(listener)),
+			// Error completing action
+			_ => Err(FtpError::from_status_code(port_result, command)),
+		}
+	}
+
+	fn eprt(&mut self) -> Result<PendingDatastream, FtpError> {
+		let listener = self.bind_datastream_listener()?;
+		let local_addr = match listener.local_addr() {
+			Ok(v) => v,
+			Err(_) => return Err(DatastreamConnectionError),
+		};
+
+		// The network protocol is 1 for IPv4 and 2 for IPv6 (RFC 2428)
+		let protocol = match local_addr {
+			SocketAddr::V4(_) => 1,
+			SocketAddr::V6(_) => 2,
+		};
+
+		let command = format!(
+			"EPRT |{}|{}|{}|\r\n",
+			protocol,
+			local_addr.ip(),
+			local_addr.port()
+		);
+		self.write_command(command.clone())?;
+
+		let eprt_result = self.wait_for_response()?;
+		match eprt_result.status {
+			// Successful action
+			ftp_status::COMMAND_OKAY => Ok(PendingDatastream::Listen(listener)),
+			// Error completing action
+			_ => Err(FtpError::from_status_code(eprt_result, command)),
+		}
+	}
+
+	fn bind_datastream_listener(&self) -> Result<TcpListener, FtpError> {
+		// Listen on the interface the control connection uses so the server can reach it
+		let local_ip = match self.reader.get_ref().get_ref().local_addr() {
+			Ok(addr) => addr.ip(),
+			Err(_) => return Err(ConnectionError),
+		};
+
+		match TcpListener::bind(SocketAddr::new(local_ip, 0)) {
+			Ok(v) => Ok(v),
+			Err(_) => Err(DatastreamConnectionError),
+		}
+	}
+
 	fn write_command(&mut self, command: String) -> Result<(), FtpError> {
 		// Send the command in bytes to the FTP server
-		match self.reader.get_mut().write_all(command.as_bytes()) {
-			Ok(_) => Ok(()),
-			Err(_) => Err(ConnectionError),
+		let stream = self.reader.get_mut();
+		match stream.write_all(command.as_bytes()).and_then(|_| stream.flush()) {
+			Ok(_) => {
+				self.last_activity = Instant::now();
+				Ok(())
+			}
+			Err(e) => Err(self.control_io_error(&e)),
+		}
+	}
+
+	/// Converts an error on the control connection, noting when the connection is gone
+	fn control_io_error(&mut self, err: &std::io::Error) -> FtpError {
+		match err.kind() {
+			std::io::ErrorKind::ConnectionReset
+			| std::io::ErrorKind::ConnectionAborted
+			| std::io::ErrorKind::BrokenPipe => self.state = FtpState::Disconnected,
+			_ => (),
+		}
+		FtpError::from_io_error(err, ConnectionError)
+	}
+
+	/// Sends a `NOOP` when the control connection was idle for longer than the keep-alive interval
+	///
+	/// Only called as a public method starts, so it never separates commands
+	/// which must follow each other, such as `REST` and `RETR` or `USER` and `PASS`.
+	fn keep_alive(&mut self) -> Result<(), FtpError> {
+		let interval = match self.keepalive {
+			Some(v) => v,
+			None => return Ok(()),
+		};
+		if self.state == FtpState::Disconnected || self.last_activity.elapsed() < interval {
+			return Ok(());
+		}
+
+		let command = "NOOP\r\n".to_string();
+		if let Err(e) = self.write_command(command.clone()) {
+			// The server most likely dropped the idle connection already
+			self.state = FtpState::Disconnected;
+			return Err(e);
+		}
+
+		let noop_result = self.wait_for_response()?;
+		match noop_result.status {
+			// Successful action
+			ftp_status::COMMAND_OKAY => Ok(()),
+			// Error completing action
+			_ => Err(FtpError::from_status_code(noop_result, command)),
 		}
 	}
 
 	fn wait_for_response(&mut self) -> Result<FtpResponse, FtpError> {
-		let mut response = String::from("");
-		match self.reader.read_line(&mut response) {
-			Ok(_) => {
-				match FtpResponse::from_str(&response) {
-					Ok(v) => Ok(v), // The response was single line
-					Err(_) => {
-						// Process multiline reply
-						let expected_end = format!("{} ", &response[0..3]);
-						while response.len() < 5 || response[0..4] != expected_end {
-							response.clear();
-							match self.reader.read_line(&mut response) {
-								Ok(_) => (),
-								Err(_) => return Err(ConnectionError),
-							}
-						}
-						FtpResponse::from_str(&response)
-					}
-				}
+		let mut response = self.read_response_line()?;
+		let mut content = String::new();
+
+		// Multiline replies start with `000-` and end with the first line starting with `000 `.
+		// The lines in between may contain anything (RFC 959 section 4.2)
+		if response.get(3..4) == Some("-") {
+			let expected_end = format!("{} ", &response[0..3]);
+			while !response.starts_with(&expected_end) {
+				content.push_str(&response);
+				response = self.read_response_line()?;
 			}
-			Err(_) => Err(ConnectionError),
+		}
+
+		let mut res = FtpResponse::from_str(&response)?;
+		content.push_str(&res.content);
+		res.content = content;
+		self.last_activity = Instant::now();
+
+		// The server is closing the connection, whatever command this was meant to answer
+		if res.status == ftp_status::SERVICE_NOT_AVAILABLE {
+			self.state = FtpState::Disconnected;
+		}
+		Ok(res)
+	}
+
+	fn read_response_line(&mut self) -> Result<String, FtpError> {
+		let mut line = vec![];
+		match self.reader.read_until(b'\n', &mut line) {
+			// The server closed the connection
+			Ok(0) => {
+				self.state = FtpState::Disconnected;
+				Err(ConnectionError)
+			}
+			Ok(_) => Ok(String::from_utf8_lossy(&line).to_string()),
+			Err(e) => Err(self.control_io_error(&e)),
 		}
 	}
 }
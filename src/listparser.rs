@@ -0,0 +1,460 @@
+use regex::Regex;
+
+use std::fmt;
+use std::time::Duration;
+use std::time::UNIX_EPOCH;
+
+use crate::error::FtpError;
+use crate::filestructure::DirectoryItem;
+use crate::filestructure::DirectoryItemType;
+use crate::time;
+
+/// A line of a `LIST` listing read by `FtpConnection::list_lenient`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListEntry {
+	/// A line the parser understood
+	Parsed(DirectoryItem),
+	/// A line the parser could not understand, as it was sent
+	Raw(String),
+}
+
+/// Turns the lines of a `LIST` listing into `DirectoryItem`s.
+///
+/// The format of `LIST` is not standardised, so every kind of server needs
+/// its own parser. `AutoParser` picks between the built-in ones, and other
+/// formats can be supported by implementing this trait and registering the
+/// parser with `FtpConnection::set_list_parser`.
+///
+/// ## Example:
+/// A parser for a server listing only `name size` pairs
+/// ```rust
+/// use ftp_lib::filestructure::{DirectoryItem, DirectoryItemType};
+/// use ftp_lib::listparser::ListParser;
+///
+/// #[derive(Debug)]
+/// struct SizeListParser;
+///
+/// impl ListParser for SizeListParser {
+///     fn parse_line(&self, line: &str) -> Result<Option<DirectoryItem>, String> {
+///         let mut parts = line.rsplitn(2, ' ');
+///         let size = parts.next().unwrap().parse().map_err(|_| "Invalid size".to_string())?;
+///         let name = parts.next().ok_or("Missing name")?;
+///         Ok(Some(DirectoryItem::new(name, DirectoryItemType::File).with_size(size)))
+///     }
+/// }
+///
+/// let items = SizeListParser.parse_listing("a.txt 10\r\nb.txt 20\r\n").unwrap();
+/// assert_eq!(items[1].name(), "b.txt");
+/// assert_eq!(items[1].size(), Some(20));
+/// ```
+pub trait ListParser: fmt::Debug + Send + Sync {
+	/// Parses a single line of the listing.
+	///
+	/// Lines which are part of the listing but don't describe an entry, such
+	/// as headers and totals, give `Ok(None)`. Lines which can't be
+	/// understood give an error explaining why.
+	fn parse_line(&self, line: &str) -> Result<Option<DirectoryItem>, String>;
+
+	/// Parses a whole listing, reporting the number of the first line which could not be parsed.
+	fn parse_listing(&self, listing: &str) -> Result<Vec<DirectoryItem>, FtpError> {
+		let mut items = vec![];
+		for (index, line) in listing.split('\n').enumerate() {
+			let line = line.trim_end_matches('\r');
+			if line.trim().is_empty() {
+				continue;
+			}
+
+			match self.parse_line(line) {
+				Ok(Some(item)) => items.push(item),
+				Ok(None) => (),
+				Err(e) => return Err(FtpError::ListingParseError(index + 1, e)),
+			}
+		}
+		Ok(items)
+	}
+
+	/// Parses a whole listing, keeping the lines which could not be parsed as `ListEntry::Raw`.
+	fn parse_listing_lenient(&self, listing: &str) -> Vec<ListEntry> {
+		listing
+			.split('\n')
+			.map(|line| line.trim_end_matches('\r'))
+			.filter(|line| !line.trim().is_empty())
+			.filter_map(|line| match self.parse_line(line) {
+				Ok(Some(item)) => Some(ListEntry::Parsed(item)),
+				Ok(None) => None,
+				Err(_) => Some(ListEntry::Raw(line.to_string())),
+			})
+			.collect()
+	}
+}
+
+/// Parses the Unix `ls -l` format used by most servers.
+///
+/// ## Example:
+/// ```rust
+/// use ftp_lib::listparser::{ListParser, UnixParser};
+///
+/// let listing = "total 8\r\ndrwxr-xr-x 2 ftp ftp 4096 Jan 15  2020 pub\r\n";
+/// let items = UnixParser.parse_listing(listing).unwrap();
+/// assert_eq!(items.len(), 1);
+/// assert_eq!(items[0].name(), "pub");
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnixParser;
+
+impl ListParser for UnixParser {
+	fn parse_line(&self, line: &str) -> Result<Option<DirectoryItem>, String> {
+		// `ls -l` starts with the number of blocks used by the directory
+		if line.starts_with("total ") {
+			return Ok(None);
+		}
+
+		line.parse().map(Some)
+	}
+}
+
+/// Parses the DOS format used by IIS and other Windows servers.
+///
+/// ## Example:
+/// ```rust
+/// use ftp_lib::filestructure::DirectoryItemType;
+/// use ftp_lib::listparser::{DosParser, ListParser};
+///
+/// let item = DosParser.parse_line("01-15-20  10:30AM       <DIR>          My Documents").unwrap().unwrap();
+/// assert_eq!(item.name(), "My Documents");
+/// assert_eq!(item.item_type(), &DirectoryItemType::Directory);
+///
+/// let item = DosParser.parse_line("01-15-2020  22:05    1024 report.txt").unwrap().unwrap();
+/// assert_eq!(item.size(), Some(1024));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DosParser;
+
+impl ListParser for DosParser {
+	fn parse_line(&self, line: &str) -> Result<Option<DirectoryItem>, String> {
+		lazy_static! {
+			static ref RE: Regex = Regex::new(concat!(
+				// Date as MM-DD-YY or MM-DD-YYYY and time in either 12 or 24 hour format
+				r"^(\d{2})-(\d{2})-(\d{2}|\d{4})\s+(\d{1,2}):(\d{2})\s*([AaPp][Mm])?\s+",
+				// Either <DIR> or the size, then the name
+				r"(<DIR>|\d+)\s+(.+)$",
+			))
+			.unwrap();
+		}
+
+		let captures = match RE.captures(line) {
+			Some(v) => v,
+			None => return Err(format!("Unrecognised entry {:?}", line)),
+		};
+
+		// Two digit years follow the usual Windows cut off
+		let year: i64 = captures[3].parse().unwrap_or(0);
+		let year = match captures[3].len() {
+			2 if year < 70 => year + 2000,
+			2 => year + 1900,
+			_ => year,
+		};
+		let mut hour: u32 = captures[4].parse().unwrap_or(0);
+		match captures.get(6).map(|half| half.as_str().to_ascii_uppercase()) {
+			Some(ref half) if half == "AM" && hour == 12 => hour = 0,
+			Some(ref half) if half == "PM" && hour < 12 => hour += 12,
+			_ => (),
+		}
+		let modified = match time::from_civil(
+			year,
+			captures[1].parse().unwrap_or(0),
+			captures[2].parse().unwrap_or(0),
+			hour,
+			captures[5].parse().unwrap_or(0),
+			0,
+			0,
+		) {
+			Some(v) => v,
+			None => return Err(format!("Invalid date in {:?}", line)),
+		};
+
+		let item = match &captures[7] {
+			"<DIR>" => DirectoryItem::new(&captures[8], DirectoryItemType::Directory),
+			size => match size.parse() {
+				Ok(size) => DirectoryItem::new(&captures[8], DirectoryItemType::File).with_size(size),
+				Err(_) => return Err(format!("Invalid size {:?}", size)),
+			},
+		};
+		Ok(Some(item.with_modified(modified)))
+	}
+}
+
+/// Parses the format used by OpenVMS servers.
+///
+/// The version number is removed from the names, and the `.DIR` extension is
+/// removed from directories. Sizes are given in blocks by VMS, which are
+/// converted to bytes assuming 512 byte blocks.
+///
+/// ## Example:
+/// ```rust
+/// use ftp_lib::listparser::{ListParser, VmsParser};
+///
+/// let listing = "Directory DISK$USER:[ANONYMOUS]\r\n\r\n\
+///     README.TXT;3     2/4    15-JAN-2020 10:30:15  [STAFF,JOE]  (RWED,RWED,RE,R)\r\n\r\n\
+///     Total of 1 file, 2/4 blocks.\r\n";
+/// let items = VmsParser.parse_listing(listing).unwrap();
+/// assert_eq!(items[0].name(), "README.TXT");
+/// assert_eq!(items[0].size(), Some(1024));
+/// assert_eq!(items[0].owner(), Some("JOE"));
+/// assert_eq!(items[0].permissions(), Some(0o754));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VmsParser;
+
+impl ListParser for VmsParser {
+	fn parse_line(&self, line: &str) -> Result<Option<DirectoryItem>, String> {
+		lazy_static! {
+			static ref RE: Regex = Regex::new(concat!(
+				// Name with its version and the blocks used, optionally followed by those allocated
+				r"^(\S+);(\d+)\s+(\d+)(?:/\d+)?\s+",
+				// Date and time, with or without seconds
+				r"(\d{1,2})-([A-Za-z]{3})-(\d{4})\s+(\d{1,2}):(\d{2})(?::(\d{2}))?(?:\.\d+)?\s+",
+				// Owner as [GROUP,OWNER] or [OWNER], then the system, owner, group and world access
+				r"\[(?:([^\],]*),)?([^\]]*)\]\s+\(([A-Z]*),([A-Z]*),([A-Z]*),([A-Z]*)\)\s*$",
+			))
+			.unwrap();
+		}
+
+		// Headers and totals surround the entries
+		if line.starts_with("Directory ") || line.starts_with("Total of ") {
+			return Ok(None);
+		}
+
+		let captures = match RE.captures(line) {
+			Some(v) => v,
+			None => return Err(format!("Unrecognised entry {:?}", line)),
+		};
+
+		let modified = time::parse_month(&captures[5]).and_then(|month| {
+			time::from_civil(
+				captures[6].parse().ok()?,
+				month,
+				captures[4].parse().ok()?,
+				captures[7].parse().ok()?,
+				captures[8].parse().ok()?,
+				captures.get(9).map_or(Some(0), |second| second.as_str().parse().ok())?,
+				0,
+			)
+		});
+		let modified = match modified {
+			Some(v) => v,
+			None => return Err(format!("Invalid date in {:?}", line)),
+		};
+
+		let (name, item_type) = match captures[1].strip_suffix(".DIR") {
+			Some(name) => (name, DirectoryItemType::Directory),
+			None => (&captures[1], DirectoryItemType::File),
+		};
+
+		// The system's access has no Unix equivalent
+		let mut permissions = 0;
+		for (shift, access) in [(6, &captures[13]), (3, &captures[14]), (0, &captures[15])].iter() {
+			for c in access.chars() {
+				match c {
+					'R' => permissions |= 0o4 << shift,
+					'W' | 'D' => permissions |= 0o2 << shift,
+					'E' => permissions |= 0o1 << shift,
+					_ => (),
+				}
+			}
+		}
+
+		let blocks: u64 = captures[3].parse().unwrap_or(0);
+		let mut item = DirectoryItem::new(name, item_type)
+			.with_size(blocks * 512)
+			.with_permissions(permissions)
+			.with_owner(&captures[11])
+			.with_modified(modified);
+		if let Some(group) = captures.get(10) {
+			item = item.with_group(group.as_str());
+		}
+		Ok(Some(item))
+	}
+
+	fn parse_listing(&self, listing: &str) -> Result<Vec<DirectoryItem>, FtpError> {
+		let mut items = vec![];
+		for (line_number, line) in VmsParser::join_wrapped(listing) {
+			match self.parse_line(&line) {
+				Ok(Some(item)) => items.push(item),
+				Ok(None) => (),
+				Err(e) => return Err(FtpError::ListingParseError(line_number, e)),
+			}
+		}
+		Ok(items)
+	}
+
+	fn parse_listing_lenient(&self, listing: &str) -> Vec<ListEntry> {
+		VmsParser::join_wrapped(listing)
+			.into_iter()
+			.filter_map(|(_, line)| match self.parse_line(&line) {
+				Ok(Some(item)) => Some(ListEntry::Parsed(item)),
+				Ok(None) => None,
+				Err(_) => Some(ListEntry::Raw(line)),
+			})
+			.collect()
+	}
+}
+
+impl VmsParser {
+	/// Splits the listing into its non-empty lines along with their line numbers.
+	///
+	/// Long names push the rest of the entry onto the next line, so those
+	/// lines are joined back up.
+	fn join_wrapped(listing: &str) -> Vec<(usize, String)> {
+		let mut lines = vec![];
+		let mut wrapped_name: Option<(usize, &str)> = None;
+		for (index, line) in listing.split('\n').enumerate() {
+			let line = line.trim_end_matches('\r');
+			if line.trim().is_empty() {
+				continue;
+			}
+			if wrapped_name.is_none() && line.contains(';') && !line.trim().contains(' ') {
+				wrapped_name = Some((index + 1, line.trim()));
+				continue;
+			}
+
+			match wrapped_name.take() {
+				Some((line_number, name)) => {
+					lines.push((line_number, format!("{} {}", name, line.trim_start())))
+				}
+				None => lines.push((index + 1, line.to_string())),
+			}
+		}
+
+		// A name at the very end never got its details
+		if let Some((line_number, name)) = wrapped_name {
+			lines.push((line_number, name.to_string()));
+		}
+		lines
+	}
+}
+
+/// Parses the Easily Parsed LIST Format (EPLF).
+///
+/// ## Example:
+/// ```rust
+/// use ftp_lib::listparser::{EplfParser, ListParser};
+///
+/// let item = EplfParser.parse_line("+i8388621.48594,m825718503,r,s280,\tdjb.html").unwrap().unwrap();
+/// assert_eq!(item.name(), "djb.html");
+/// assert_eq!(item.size(), Some(280));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EplfParser;
+
+impl ListParser for EplfParser {
+	fn parse_line(&self, line: &str) -> Result<Option<DirectoryItem>, String> {
+		// Every entry is `+facts\tname`, with the facts separated by commas
+		let (facts, name) = match (line.strip_prefix('+'), line.find('\t')) {
+			(Some(_), Some(split)) => (&line[1..split], &line[split + 1..]),
+			_ => return Err(format!("Unrecognised entry {:?}", line)),
+		};
+		if name.is_empty() {
+			return Err("Missing name".to_string());
+		}
+
+		let mut item_type = DirectoryItemType::File;
+		let mut size = None;
+		let mut modified = None;
+		let mut permissions = None;
+		for fact in facts.split(',').filter(|fact| !fact.is_empty()) {
+			let invalid = || format!("Invalid fact {:?}", fact);
+			match fact.as_bytes()[0] {
+				b'/' => item_type = DirectoryItemType::Directory,
+				b's' => size = Some(fact[1..].parse::<u64>().map_err(|_| invalid())?),
+				b'm' => {
+					let seconds = fact[1..].parse::<u64>().map_err(|_| invalid())?;
+					// Adding a time beyond what SystemTime can hold would panic
+					modified = Some(
+						UNIX_EPOCH
+							.checked_add(Duration::from_secs(seconds))
+							.ok_or_else(invalid)?,
+					);
+				}
+				b'u' if fact.starts_with("up") => {
+					permissions = Some(u32::from_str_radix(&fact[2..], 8).map_err(|_| invalid())?);
+				}
+				// Other facts, such as the identifier, carry nothing a DirectoryItem holds
+				_ => (),
+			}
+		}
+
+		let mut item = DirectoryItem::new(name, item_type);
+		if let Some(size) = size {
+			item = item.with_size(size);
+		}
+		if let Some(modified) = modified {
+			item = item.with_modified(modified);
+		}
+		if let Some(permissions) = permissions {
+			item = item.with_permissions(permissions);
+		}
+		Ok(Some(item))
+	}
+}
+
+/// Detects the format of a listing and parses it with the matching built-in parser.
+///
+/// The first line one of the parsers understands decides the format of the
+/// whole listing. This is the parser used by `FtpConnection::list` unless
+/// another one is set.
+///
+/// ## Example:
+/// ```rust
+/// use ftp_lib::listparser::{AutoParser, ListParser};
+///
+/// let unix = AutoParser.parse_listing("-rw-r--r-- 1 ftp ftp 12 Jan 15  2020 a.txt\r\n").unwrap();
+/// let dos = AutoParser.parse_listing("01-15-20  10:30AM  12 a.txt\r\n").unwrap();
+/// assert_eq!(unix[0].name(), dos[0].name());
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AutoParser;
+
+impl AutoParser {
+	const PARSERS: [&'static dyn ListParser; 4] = [&UnixParser, &DosParser, &EplfParser, &VmsParser];
+
+	/// Finds the parser understanding the first entry of the listing.
+	fn detect(listing: &str) -> Option<&'static dyn ListParser> {
+		listing
+			.split('\n')
+			.map(|line| line.trim_end_matches('\r'))
+			.filter(|line| !line.trim().is_empty())
+			.find_map(|line| {
+				AutoParser::PARSERS
+					.iter()
+					.find(|parser| matches!(parser.parse_line(line), Ok(Some(_))))
+					.copied()
+			})
+	}
+}
+
+impl ListParser for AutoParser {
+	fn parse_line(&self, line: &str) -> Result<Option<DirectoryItem>, String> {
+		for parser in AutoParser::PARSERS.iter() {
+			if let Ok(item) = parser.parse_line(line) {
+				return Ok(item);
+			}
+		}
+		Err(format!("Unrecognised entry {:?}", line))
+	}
+
+	fn parse_listing(&self, listing: &str) -> Result<Vec<DirectoryItem>, FtpError> {
+		match AutoParser::detect(listing) {
+			Some(parser) => parser.parse_listing(listing),
+			// Nothing was recognised, which is only fine when there is nothing listed
+			None => UnixParser.parse_listing(listing),
+		}
+	}
+
+	fn parse_listing_lenient(&self, listing: &str) -> Vec<ListEntry> {
+		match AutoParser::detect(listing) {
+			Some(parser) => parser.parse_listing_lenient(listing),
+			None => UnixParser.parse_listing_lenient(listing),
+		}
+	}
+}
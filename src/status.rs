@@ -63,6 +63,11 @@ pub mod ftp_status {
     pub const DATA_CLOSED_ABORTING: u32 = 426;
     /// Entering Passive Mode (h1,h2,h3,h4,p1,p2).
     pub const ENTERING_PASSIVE: u32 = 227;
+    /// Entering Extended Passive Mode (|||port|).
+    pub const ENTERING_EXTENDED_PASSIVE: u32 = 229;
+    /// Security data exchange complete (RFC 2228).
+    /// Sent in response to `AUTH` once the TLS handshake may begin.
+    pub const SECURITY_EXCHANGE_COMPLETE: u32 = 234;
     /// User logged in, proceed.
     pub const LOGGED_IN: u32 = 230;
     /// Not logged in.
@@ -0,0 +1,88 @@
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::net::Shutdown;
+use std::net::TcpStream;
+
+/// A connection to the FTP server, either in plain text or protected with TLS.
+///
+/// Used for both the control connection and the data connections, so the rest
+/// of the library does not need to care whether FTPS is being used.
+#[derive(Debug)]
+pub(crate) enum FtpStream {
+	Plain(TcpStream),
+	#[cfg(feature = "tls")]
+	Tls(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>),
+}
+
+impl FtpStream {
+	/// Gets the underlying TCP connection.
+	pub(crate) fn get_ref(&self) -> &TcpStream {
+		match self {
+			FtpStream::Plain(stream) => stream,
+			#[cfg(feature = "tls")]
+			FtpStream::Tls(stream) => stream.get_ref(),
+		}
+	}
+
+	/// Gets the underlying TCP connection when nothing is layered on top of it.
+	pub(crate) fn plain_mut(&mut self) -> Option<&mut TcpStream> {
+		match self {
+			FtpStream::Plain(stream) => Some(stream),
+			#[cfg(feature = "tls")]
+			FtpStream::Tls(_) => None,
+		}
+	}
+
+	/// Closes the connection, letting the server know when TLS is in use.
+	pub(crate) fn shutdown(&mut self) -> io::Result<()> {
+		let shutdown = match self {
+			FtpStream::Plain(stream) => stream.shutdown(Shutdown::Both),
+			#[cfg(feature = "tls")]
+			FtpStream::Tls(stream) => {
+				// The server may have closed its side already, which makes close_notify moot
+				stream.conn.send_close_notify();
+				let _ = stream.flush();
+				stream.sock.shutdown(Shutdown::Write)
+			}
+		};
+
+		match shutdown {
+			// The server closed the connection first, so there is nothing left to shut down
+			Err(ref e) if e.kind() == io::ErrorKind::NotConnected => Ok(()),
+			other => other,
+		}
+	}
+}
+
+impl Read for FtpStream {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		match self {
+			FtpStream::Plain(stream) => stream.read(buf),
+			#[cfg(feature = "tls")]
+			FtpStream::Tls(stream) => match stream.read(buf) {
+				// Many servers close the data connection without a close_notify
+				Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(0),
+				other => other,
+			},
+		}
+	}
+}
+
+impl Write for FtpStream {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		match self {
+			FtpStream::Plain(stream) => stream.write(buf),
+			#[cfg(feature = "tls")]
+			FtpStream::Tls(stream) => stream.write(buf),
+		}
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		match self {
+			FtpStream::Plain(stream) => stream.flush(),
+			#[cfg(feature = "tls")]
+			FtpStream::Tls(stream) => stream.flush(),
+		}
+	}
+}
@@ -0,0 +1,155 @@
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+/// Counts the days from 1970-01-01 to the given date of the Gregorian calendar.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+	// Shift the year to start in March so the leap day is the last day of the year
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = (if year >= 0 { year } else { year - 399 }) / 400;
+	let year_of_era = year - era * 400;
+	let month_index = (i64::from(month) + 9) % 12;
+	let day_of_year = (153 * month_index + 2) / 5 + i64::from(day) - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+	era * 146_097 + day_of_era - 719_468
+}
+
+/// Finds the date of the Gregorian calendar the given number of days after 1970-01-01.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+	let days = days + 719_468;
+	let era = (if days >= 0 { days } else { days - 146_096 }) / 146_097;
+	let day_of_era = days - era * 146_097;
+	let year_of_era =
+		(day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month_index = (5 * day_of_year + 2) / 153;
+	let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+	let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+
+	// The year was counted from March, so January and February belong to the next one
+	let year = year_of_era + era * 400;
+	(if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+/// Gets the year of the given time in UTC.
+pub(crate) fn year_of(time: SystemTime) -> i64 {
+	let days = match time.duration_since(UNIX_EPOCH) {
+		Ok(since) => (since.as_secs() / 86_400) as i64,
+		Err(e) => -(e.duration().as_secs().div_ceil(86_400) as i64),
+	};
+	civil_from_days(days).0
+}
+
+/// Gets the number of the month with the given English abbreviation, such as `Jan` or `DEC`.
+pub(crate) fn parse_month(name: &str) -> Option<u32> {
+	const MONTHS: [&str; 12] = [
+		"jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+	];
+	MONTHS
+		.iter()
+		.position(|month| month.eq_ignore_ascii_case(name))
+		.map(|index| index as u32 + 1)
+}
+
+/// Gets the number of days in the given month.
+pub(crate) fn days_in_month(year: i64, month: u32) -> u32 {
+	match month {
+		2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+		2 => 28,
+		4 | 6 | 9 | 11 => 30,
+		_ => 31,
+	}
+}
+
+/// Converts a UTC date and time to a `SystemTime`, checking every field is in range.
+pub(crate) fn from_civil(
+	year: i64,
+	month: u32,
+	day: u32,
+	hour: u32,
+	minute: u32,
+	second: u32,
+	nanos: u32,
+) -> Option<SystemTime> {
+	// A second of 60 is allowed for leap seconds
+	if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+		return None;
+	}
+	if hour > 23 || minute > 59 || second > 60 || nanos >= 1_000_000_000 {
+		return None;
+	}
+
+	let seconds = days_from_civil(year, month, day) * 86_400
+		+ i64::from(hour) * 3_600
+		+ i64::from(minute) * 60
+		+ i64::from(second);
+
+	if seconds >= 0 {
+		UNIX_EPOCH.checked_add(Duration::new(seconds as u64, nanos))
+	} else {
+		UNIX_EPOCH
+			.checked_sub(Duration::from_secs(seconds.unsigned_abs()))?
+			.checked_add(Duration::from_nanos(u64::from(nanos)))
+	}
+}
+
+/// Parses a time in the `YYYYMMDDHHMMSS[.sss]` format used by `MDTM` and the
+/// `modify` fact of `MLST` (RFC 3659), which is always in UTC.
+pub(crate) fn parse_time_val(s: &str) -> Option<SystemTime> {
+	let (whole, fraction) = match s.find('.') {
+		Some(dot) => (&s[..dot], Some(&s[dot + 1..])),
+		None => (s, None),
+	};
+	if whole.len() != 14 || !whole.bytes().all(|b| b.is_ascii_digit()) {
+		return None;
+	}
+
+	// Only the first 9 digits of the fraction fit in nanoseconds
+	let nanos = match fraction {
+		Some(fraction) if fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit()) => {
+			return None
+		}
+		Some(fraction) => {
+			let digits = &fraction[..fraction.len().min(9)];
+			digits.parse::<u32>().ok()? * 10u32.pow(9 - digits.len() as u32)
+		}
+		None => 0,
+	};
+
+	from_civil(
+		whole[0..4].parse().ok()?,
+		whole[4..6].parse().ok()?,
+		whole[6..8].parse().ok()?,
+		whole[8..10].parse().ok()?,
+		whole[10..12].parse().ok()?,
+		whole[12..14].parse().ok()?,
+		nanos,
+	)
+}
+
+/// Formats a time in the `YYYYMMDDHHMMSS` format used by `MDTM` and `MFMT` (RFC 3659), in UTC.
+///
+/// Fractions of a second are left out, as not every server accepts them.
+pub(crate) fn format_time_val(time: SystemTime) -> String {
+	let seconds = match time.duration_since(UNIX_EPOCH) {
+		Ok(since) => since.as_secs() as i64,
+		// Round down, so a time a fraction of a second before a minute stays in it
+		Err(e) => {
+			let before = e.duration();
+			-(before.as_secs() as i64) - i64::from(before.subsec_nanos() > 0)
+		}
+	};
+	let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+	let second_of_day = seconds.rem_euclid(86_400);
+
+	format!(
+		"{:04}{:02}{:02}{:02}{:02}{:02}",
+		year,
+		month,
+		day,
+		second_of_day / 3_600,
+		second_of_day / 60 % 60,
+		second_of_day % 60
+	)
+}
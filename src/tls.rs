@@ -0,0 +1,71 @@
+use std::convert::TryFrom;
+use std::net::TcpStream;
+use std::sync::Arc;
+
+use rustls::pki_types::ServerName;
+use rustls::ClientConfig;
+use rustls::ClientConnection;
+use rustls::StreamOwned;
+
+use crate::error::FtpError;
+use crate::stream::FtpStream;
+
+/// The settings used to protect connections to the FTP server with TLS.
+///
+/// The same settings are used for the control connection and every data
+/// connection, which lets rustls resume the control connection's session on
+/// the data connections as many servers require.
+///
+/// ## Example:
+/// Trust a self-signed certificate for a local server
+/// ```rust,no_run
+/// use ftp_lib::rustls::{ClientConfig, RootCertStore};
+/// use ftp_lib::tls::TlsConfig;
+/// use std::sync::Arc;
+///
+/// let mut roots = RootCertStore::empty();
+/// roots.add(std::fs::read("ca.der").unwrap().into()).unwrap();
+///
+/// let config = ClientConfig::builder()
+///     .with_root_certificates(roots)
+///     .with_no_client_auth();
+/// let tls_config = TlsConfig::new(Arc::new(config), "localhost").unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+	config: Arc<ClientConfig>,
+	server_name: ServerName<'static>,
+}
+
+impl TlsConfig {
+	/// Creates the TLS settings for the server with the given name.
+	///
+	/// The name is what the server's certificate is checked against, and can
+	/// be either a DNS name or an IP address.
+	pub fn new(config: Arc<ClientConfig>, server_name: &str) -> Result<TlsConfig, FtpError> {
+		match ServerName::try_from(server_name.to_string()) {
+			Ok(server_name) => Ok(TlsConfig {
+				config,
+				server_name,
+			}),
+			Err(_) => Err(FtpError::TlsError(format!("Invalid server name {:?}", server_name))),
+		}
+	}
+
+	/// Performs the TLS handshake over the given connection.
+	pub(crate) fn wrap(&self, mut stream: TcpStream) -> Result<FtpStream, FtpError> {
+		let mut conn = match ClientConnection::new(self.config.clone(), self.server_name.clone()) {
+			Ok(v) => v,
+			Err(e) => return Err(FtpError::TlsError(e.to_string())),
+		};
+
+		// Complete the handshake now so failures are reported before any data is sent
+		while conn.is_handshaking() {
+			if let Err(e) = conn.complete_io(&mut stream) {
+				return Err(FtpError::from_io_error(&e, FtpError::TlsError(e.to_string())));
+			}
+		}
+
+		Ok(FtpStream::Tls(Box::new(StreamOwned::new(conn, stream))))
+	}
+}
@@ -0,0 +1,397 @@
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+use crate::error::FtpError;
+use crate::stream::FtpStream;
+use crate::FtpConnection;
+use crate::FtpResponse;
+use crate::TransferType;
+
+/// Size of the chunks read from the data connection while translating line endings
+const ASCII_CHUNK_SIZE: usize = 8192;
+
+/// Streams the contents of a file being retrieved from the FTP server.
+///
+/// Returned by `FtpConnection::retr_reader`, the reader holds on to the
+/// connection until the transfer is completed. Calling `finish` closes the
+/// data connection and checks the final reply from the server, while `abort`
+/// stops the transfer early. If the reader is dropped instead, the reply is
+/// still read to keep the control connection in sync, but any error it
+/// contains is discarded.
+///
+/// When the transfer type is `TransferType::Ascii`, the CRLF line endings
+/// sent by the server are translated to LF.
+#[derive(Debug)]
+pub struct FtpReader<'a> {
+	conn: &'a mut FtpConnection,
+	datastream: Option<FtpStream>,
+	command: String,
+	ascii: Option<AsciiDecoder>,
+}
+
+impl<'a> FtpReader<'a> {
+	pub(crate) fn new(conn: &'a mut FtpConnection, datastream: FtpStream, command: String) -> Self {
+		let ascii = if conn.transfer_type == Some(TransferType::Ascii) {
+			Some(AsciiDecoder::default())
+		} else {
+			None
+		};
+
+		FtpReader {
+			conn,
+			datastream: Some(datastream),
+			command,
+			ascii,
+		}
+	}
+
+	/// Closes the data connection and waits for the server to confirm the transfer.
+	pub fn finish(mut self) -> Result<(), FtpError> {
+		self.close()
+	}
+
+	/// Stops the transfer before the whole file has been read.
+	///
+	/// Sends the Telnet IP and Synch signals followed by `ABOR`, then reads
+	/// every reply the server sends for the interrupted transfer, so the
+	/// connection can be used for further commands.
+	pub fn abort(mut self) -> Result<(), FtpError> {
+		match self.datastream.take() {
+			Some(datastream) => self.conn.abort_transfer(datastream, self.command.clone()),
+			None => Ok(()),
+		}
+	}
+
+	fn close(&mut self) -> Result<(), FtpError> {
+		match self.datastream.take() {
+			Some(mut datastream) => {
+				// The server may already have closed its side, so errors are ignored here
+				let _ = datastream.shutdown();
+				self.conn.finish_transfer(self.command.clone()).map(|_| ())
+			}
+			None => Ok(()),
+		}
+	}
+}
+
+impl<'a> Read for FtpReader<'a> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let datastream = match self.datastream {
+			Some(ref mut datastream) => datastream,
+			None => return Ok(0),
+		};
+
+		match self.ascii {
+			Some(ref mut decoder) => decoder.read(datastream, buf),
+			None => datastream.read(buf),
+		}
+	}
+}
+
+impl<'a> Drop for FtpReader<'a> {
+	fn drop(&mut self) {
+		let _ = self.close();
+	}
+}
+
+/// Streams data into a file being stored on the FTP server.
+///
+/// Returned by `FtpConnection::stor_writer`, `append_writer` and
+/// `stou_writer`, the writer holds on to the connection until the transfer is
+/// completed. Calling `finish` closes the data connection, which tells the
+/// server the file is complete, and checks the final reply, while `abort`
+/// stops the transfer early. Only `finish` commits the file: dropping a
+/// writer that was not finished aborts the transfer like `abort`, so a write
+/// that failed halfway is never mistaken for a complete upload. Any error
+/// from aborting on drop is discarded.
+///
+/// When the transfer type is `TransferType::Ascii`, LF line endings are
+/// translated to the CRLF line endings required on the wire.
+#[derive(Debug)]
+pub struct FtpWriter<'a> {
+	conn: &'a mut FtpConnection,
+	datastream: Option<FtpStream>,
+	command: String,
+	ascii: Option<AsciiEncoder>,
+	unique_name: Option<String>,
+}
+
+impl<'a> FtpWriter<'a> {
+	pub(crate) fn new(conn: &'a mut FtpConnection, datastream: FtpStream, command: String) -> Self {
+		let ascii = if conn.transfer_type == Some(TransferType::Ascii) {
+			Some(AsciiEncoder::default())
+		} else {
+			None
+		};
+
+		FtpWriter {
+			conn,
+			datastream: Some(datastream),
+			command,
+			ascii,
+			unique_name: None,
+		}
+	}
+
+	pub(crate) fn with_unique_name(mut self, unique_name: Option<String>) -> Self {
+		self.unique_name = unique_name;
+		self
+	}
+
+	/// Gets the name the server chose for the file when storing it with `STOU`.
+	///
+	/// Only available when the server announced the name as the transfer
+	/// started. `FtpConnection::store_unique` also checks the final reply.
+	pub fn unique_name(&self) -> Option<&str> {
+		self.unique_name.as_deref()
+	}
+
+	/// Closes the data connection and waits for the server to confirm the transfer.
+	pub fn finish(mut self) -> Result<(), FtpError> {
+		self.close().map(|_| ())
+	}
+
+	/// Closes the data connection, returning the server's final reply.
+	pub(crate) fn finish_with_reply(mut self) -> Result<FtpResponse, FtpError> {
+		match self.close()? {
+			Some(res) => Ok(res),
+			None => Err(FtpError::DatastreamConnectionError),
+		}
+	}
+
+	/// Stops the transfer before the whole file has been written.
+	///
+	/// Sends the Telnet IP and Synch signals followed by `ABOR`, then reads
+	/// every reply the server sends for the interrupted transfer, so the
+	/// connection can be used for further commands. Whatever the server
+	/// already received may be left behind in the file.
+	pub fn abort(mut self) -> Result<(), FtpError> {
+		match self.datastream.take() {
+			Some(datastream) => self.conn.abort_transfer(datastream, self.command.clone()),
+			None => Ok(()),
+		}
+	}
+
+	fn close(&mut self) -> Result<Option<FtpResponse>, FtpError> {
+		match self.datastream.take() {
+			Some(mut datastream) => {
+				// Closing the data connection marks the end of the file
+				let closed = datastream
+					.flush()
+					.and_then(|_| datastream.shutdown());
+
+				// The final reply is always read so the control connection stays in sync
+				let res = self.conn.finish_transfer(self.command.clone())?;
+				match closed {
+					Ok(_) => Ok(Some(res)),
+					Err(e) => Err(FtpError::from_io_error(&e, FtpError::DatastreamConnectionError)),
+				}
+			}
+			None => Ok(None),
+		}
+	}
+}
+
+impl<'a> Write for FtpWriter<'a> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let datastream = match self.datastream {
+			Some(ref mut datastream) => datastream,
+			None => return Err(io::Error::from(io::ErrorKind::NotConnected)),
+		};
+
+		match self.ascii {
+			Some(ref mut encoder) => encoder.write(datastream, buf),
+			None => datastream.write(buf),
+		}
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		match self.datastream {
+			Some(ref mut datastream) => datastream.flush(),
+			None => Ok(()),
+		}
+	}
+}
+
+impl<'a> Drop for FtpWriter<'a> {
+	fn drop(&mut self) {
+		// Closing the data connection would tell the server the file is complete
+		if let Some(datastream) = self.datastream.take() {
+			let _ = self.conn.abort_transfer(datastream, self.command.clone());
+		}
+	}
+}
+
+/// Translates the CRLF line endings of an ASCII download to LF.
+#[derive(Debug, Default)]
+struct AsciiDecoder {
+	/// Set when the last byte read was a CR, which may be followed by an LF in the next chunk
+	pending_cr: bool,
+	/// Translated data which did not fit in the caller's buffer yet
+	decoded: Vec<u8>,
+	decoded_pos: usize,
+}
+
+impl AsciiDecoder {
+	fn read<R: Read>(&mut self, datastream: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+		// Nothing fits, so there is no reason to wait on the data connection
+		if buf.is_empty() {
+			return Ok(0);
+		}
+
+		let mut raw = [0; ASCII_CHUNK_SIZE];
+
+		// A chunk made of a single CR translates to nothing yet, so keep reading
+		while self.decoded_pos == self.decoded.len() {
+			self.decoded.clear();
+			self.decoded_pos = 0;
+
+			let read = datastream.read(&mut raw)?;
+			if read == 0 {
+				// A CR at the very end of the file was not part of a line ending
+				if self.pending_cr {
+					self.pending_cr = false;
+					self.decoded.push(b'\r');
+					continue;
+				}
+				return Ok(0);
+			}
+
+			for &byte in raw[..read].iter() {
+				if self.pending_cr {
+					self.pending_cr = false;
+					if byte != b'\n' {
+						self.decoded.push(b'\r');
+					}
+				}
+
+				if byte == b'\r' {
+					self.pending_cr = true;
+				} else {
+					self.decoded.push(byte);
+				}
+			}
+		}
+
+		let remaining = &self.decoded[self.decoded_pos..];
+		let len = remaining.len().min(buf.len());
+		buf[..len].copy_from_slice(&remaining[..len]);
+		self.decoded_pos += len;
+		Ok(len)
+	}
+}
+
+/// Translates the LF line endings of an ASCII upload to CRLF.
+#[derive(Debug, Default)]
+struct AsciiEncoder {
+	/// Set when the last byte written was a CR, so an LF starting the next write is left alone
+	last_cr: bool,
+}
+
+impl AsciiEncoder {
+	fn write<W: Write>(&mut self, datastream: &mut W, buf: &[u8]) -> io::Result<usize> {
+		let mut encoded = Vec::with_capacity(buf.len() + buf.len() / 16);
+		for &byte in buf.iter() {
+			// Line endings which are already CRLF are sent unchanged
+			if byte == b'\n' && !self.last_cr {
+				encoded.push(b'\r');
+			}
+			encoded.push(byte);
+			self.last_cr = byte == b'\r';
+		}
+
+		datastream.write_all(&encoded)?;
+		Ok(buf.len())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Hands out the given chunks one read at a time, like a data connection would
+	struct Chunks(Vec<&'static [u8]>);
+
+	impl Read for Chunks {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			if self.0.is_empty() {
+				return Ok(0);
+			}
+			let chunk = self.0.remove(0);
+			buf[..chunk.len()].copy_from_slice(chunk);
+			Ok(chunk.len())
+		}
+	}
+
+	fn decode(chunks: Vec<&'static [u8]>) -> Vec<u8> {
+		let mut decoder = AsciiDecoder::default();
+		let mut datastream = Chunks(chunks);
+		let mut decoded = Vec::new();
+		let mut buf = [0; 4];
+		loop {
+			match decoder.read(&mut datastream, &mut buf).unwrap() {
+				0 => return decoded,
+				len => decoded.extend_from_slice(&buf[..len]),
+			}
+		}
+	}
+
+	fn encode(chunks: &[&[u8]]) -> Vec<u8> {
+		let mut encoder = AsciiEncoder::default();
+		let mut encoded = Vec::new();
+		for chunk in chunks {
+			assert_eq!(encoder.write(&mut encoded, chunk).unwrap(), chunk.len());
+		}
+		encoded
+	}
+
+	#[test]
+	fn decodes_crlf_split_across_chunks() {
+		assert_eq!(decode(vec![b"one\r", b"\ntwo\r\n"]), b"one\ntwo\n");
+	}
+
+	#[test]
+	fn decodes_chunk_of_a_single_cr() {
+		assert_eq!(decode(vec![b"one", b"\r", b"\ntwo"]), b"one\ntwo");
+	}
+
+	#[test]
+	fn keeps_lone_cr() {
+		assert_eq!(decode(vec![b"one\rtwo\r", b"three"]), b"one\rtwo\rthree");
+	}
+
+	#[test]
+	fn keeps_final_cr() {
+		assert_eq!(decode(vec![b"one\r\ntwo\r"]), b"one\ntwo\r");
+		assert_eq!(decode(vec![b"one", b"\r"]), b"one\r");
+	}
+
+	#[test]
+	fn reads_nothing_into_empty_buffer() {
+		let mut decoder = AsciiDecoder::default();
+		let mut datastream = Chunks(vec![b"one\r\n"]);
+		assert_eq!(decoder.read(&mut datastream, &mut []).unwrap(), 0);
+
+		// The data is still there for the next read
+		let mut buf = [0; 8];
+		assert_eq!(decoder.read(&mut datastream, &mut buf).unwrap(), 4);
+		assert_eq!(&buf[..4], b"one\n");
+	}
+
+	#[test]
+	fn encodes_lf_as_crlf() {
+		assert_eq!(encode(&[b"one\ntwo\n"]), b"one\r\ntwo\r\n");
+	}
+
+	#[test]
+	fn leaves_crlf_split_across_writes() {
+		assert_eq!(encode(&[b"one\r", b"\ntwo"]), b"one\r\ntwo");
+	}
+
+	#[test]
+	fn keeps_lone_and_final_cr() {
+		assert_eq!(encode(&[b"one\rtwo\r"]), b"one\rtwo\r");
+		assert_eq!(encode(&[b"one\r", b"two\n"]), b"one\rtwo\r\n");
+	}
+}
@@ -0,0 +1,137 @@
+//! An FTP server run inside the test process, answering from a table of replies.
+// Each test file only uses part of the fixture
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use ftp_lib::{FtpConnection, FtpTimeouts};
+use socket2::SockRef;
+
+/// What the server does in answer to a command
+pub enum Reply {
+	/// Sends a line of the reply, such as `200 OK`
+	Line(String),
+	/// Opens a data port and announces it with a 227 reply
+	Passive,
+	/// Accepts the data connection on the port opened last and sends the data, keeping it open
+	Data(&'static [u8]),
+	/// Closes the data connection
+	CloseData,
+	/// Closes the control connection
+	Close,
+	/// Resets the control connection instead of closing it cleanly
+	Reset,
+}
+
+impl From<&str> for Reply {
+	fn from(line: &str) -> Reply {
+		Reply::Line(line.to_string())
+	}
+}
+
+/// Answers the commands most tests need
+pub fn reply(command: &str) -> Vec<Reply> {
+	let line = match command.split(' ').next().unwrap_or("") {
+		"USER" => "331 Password required",
+		"PASS" => "230 Logged in",
+		"PWD" => "257 \"/\" is the current directory",
+		"TYPE" => "200 Type set",
+		"NOOP" => "200 OK",
+		"QUIT" => "221 Goodbye",
+		_ => "502 Command not implemented",
+	};
+	vec![line.into()]
+}
+
+/// Connects to a test server.
+pub fn connect(addr: SocketAddr) -> FtpConnection {
+	// Fail rather than hang if the client and server ever wait on each other
+	let timeout = Some(Duration::from_secs(5));
+	FtpConnection::builder()
+		.timeouts(FtpTimeouts {
+			connect: timeout,
+			control: timeout,
+			data: timeout,
+		})
+		.connect(addr)
+		.unwrap()
+}
+
+/// Starts a server answering each command with `respond`, and connects to it.
+///
+/// Every command the server receives is passed on through the returned channel.
+pub fn start<F>(respond: F) -> (FtpConnection, Receiver<String>, thread::JoinHandle<()>)
+where
+	F: FnMut(&str) -> Vec<Reply> + Send + 'static,
+{
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+	let (sender, commands) = mpsc::channel();
+	let server = thread::spawn(move || serve(listener, respond, sender));
+
+	(connect(addr), commands, server)
+}
+
+fn serve<F>(listener: TcpListener, mut respond: F, commands: mpsc::Sender<String>)
+where
+	F: FnMut(&str) -> Vec<Reply>,
+{
+	let (control, _) = listener.accept().unwrap();
+	let mut writer = control.try_clone().unwrap();
+	let mut reader = BufReader::new(control);
+	let mut passive: Option<TcpListener> = None;
+	// Only held to keep the data connection open until Reply::CloseData
+	let mut _data: Option<TcpStream> = None;
+
+	writer.write_all(b"220 Ready\r\n").unwrap();
+	loop {
+		let mut line = vec![];
+		if reader.read_until(b'\n', &mut line).unwrap() == 0 {
+			return;
+		}
+		// Leaves out the Telnet signals sent before ABOR
+		line.retain(|byte| byte.is_ascii());
+		let command = String::from_utf8_lossy(&line).trim_end().to_string();
+		let replies = respond(&command);
+		commands.send(command).unwrap();
+
+		for reply in replies {
+			match reply {
+				Reply::Line(line) => {
+					writer.write_all(format!("{}\r\n", line).as_bytes()).unwrap();
+					// The server closes the connection after a 421
+					if line.starts_with("421") {
+						return;
+					}
+				}
+				Reply::Passive => {
+					let data_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+					let port = data_listener.local_addr().unwrap().port();
+					passive = Some(data_listener);
+					let line = format!(
+						"227 Entering Passive Mode (127,0,0,1,{},{})\r\n",
+						port / 256,
+						port % 256
+					);
+					writer.write_all(line.as_bytes()).unwrap();
+				}
+				Reply::Data(bytes) => {
+					let (mut stream, _) = passive.take().unwrap().accept().unwrap();
+					stream.write_all(bytes).unwrap();
+					_data = Some(stream);
+				}
+				Reply::CloseData => _data = None,
+				Reply::Close => return,
+				Reply::Reset => {
+					// Without lingering, closing the socket sends an RST
+					SockRef::from(&writer).set_linger(Some(Duration::ZERO)).unwrap();
+					return;
+				}
+			}
+		}
+	}
+}
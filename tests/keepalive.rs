@@ -0,0 +1,103 @@
+//! Keep-alive `NOOP`s and disconnect tracking against an in-process server.
+
+mod common;
+
+use std::thread;
+use std::time::Duration;
+
+use common::Reply;
+use ftp_lib::error::FtpError;
+use ftp_lib::FtpState;
+
+fn respond(command: &str) -> Vec<Reply> {
+	match command.split(' ').next().unwrap_or("") {
+		"RNFR" => vec!["350 Ready for destination".into()],
+		"RNTO" => vec!["250 Renamed".into()],
+		_ => common::reply(command),
+	}
+}
+
+#[test]
+fn noop_is_sent_once_idle_for_the_interval() {
+	let (mut ftp_conn, commands, server) = common::start(respond);
+	ftp_conn.set_keepalive(Some(Duration::from_millis(300)));
+
+	ftp_conn.pwd().unwrap();
+	ftp_conn.pwd().unwrap();
+	thread::sleep(Duration::from_millis(400));
+	ftp_conn.pwd().unwrap();
+	ftp_conn.pwd().unwrap();
+
+	ftp_conn.quit().unwrap();
+	server.join().unwrap();
+	assert_eq!(
+		commands.try_iter().collect::<Vec<_>>(),
+		["PWD", "PWD", "NOOP", "PWD", "PWD", "QUIT"]
+	);
+}
+
+#[test]
+fn noop_never_splits_commands_that_belong_together() {
+	let (mut ftp_conn, commands, server) = common::start(respond);
+	// Every method now starts with a NOOP
+	ftp_conn.set_keepalive(Some(Duration::ZERO));
+
+	ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	ftp_conn.rename("old.txt", "new.txt").unwrap();
+
+	ftp_conn.quit().unwrap();
+	server.join().unwrap();
+	assert_eq!(
+		commands.try_iter().collect::<Vec<_>>(),
+		[
+			"NOOP",
+			"USER anonymous",
+			"PASS fake@email.service",
+			"NOOP",
+			"RNFR old.txt",
+			"RNTO new.txt",
+			"QUIT"
+		]
+	);
+}
+
+#[test]
+fn idle_timeout_reply_disconnects() {
+	let (mut ftp_conn, commands, server) = common::start(|command| match command {
+		"NOOP" => vec!["421 Timeout".into()],
+		_ => respond(command),
+	});
+	ftp_conn.set_keepalive(Some(Duration::ZERO));
+
+	assert!(ftp_conn.pwd().is_err());
+	assert_eq!(ftp_conn.state(), FtpState::Disconnected);
+
+	server.join().unwrap();
+	assert_eq!(commands.try_iter().collect::<Vec<_>>(), ["NOOP"]);
+}
+
+#[test]
+fn closed_connection_disconnects() {
+	let (mut ftp_conn, _commands, server) = common::start(|command| match command {
+		"PWD" => vec![Reply::Close],
+		_ => respond(command),
+	});
+
+	assert!(matches!(ftp_conn.pwd(), Err(FtpError::ConnectionError)));
+	assert_eq!(ftp_conn.state(), FtpState::Disconnected);
+
+	server.join().unwrap();
+}
+
+#[test]
+fn reset_connection_disconnects() {
+	let (mut ftp_conn, _commands, server) = common::start(|command| match command {
+		"PWD" => vec![Reply::Reset],
+		_ => respond(command),
+	});
+
+	assert!(matches!(ftp_conn.pwd(), Err(FtpError::ConnectionError)));
+	assert_eq!(ftp_conn.state(), FtpState::Disconnected);
+
+	server.join().unwrap();
+}
@@ -0,0 +1,151 @@
+//! Explicit FTPS against an in-process server with a self-signed certificate.
+//!
+//! Run with `cargo test --features tls`.
+#![cfg(feature = "tls")]
+
+mod common;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::thread;
+
+use ftp_lib::error::FtpError;
+use ftp_lib::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use ftp_lib::rustls::{ClientConfig, RootCertStore, ServerConfig, ServerConnection, StreamOwned};
+use ftp_lib::tls::TlsConfig;
+use ftp_lib::FtpConnection;
+
+trait Stream: Read + Write + Send {}
+impl<T: Read + Write + Send> Stream for T {}
+
+/// Serves a single client, with `hello.txt` as the only file
+fn serve(listener: TcpListener, config: Arc<ServerConfig>) {
+	let (control, _) = listener.accept().unwrap();
+	let raw = control.try_clone().unwrap();
+	let mut reader: BufReader<Box<dyn Stream>> = BufReader::new(Box::new(control));
+	let mut passive: Option<TcpListener> = None;
+
+	reply(&mut reader, "220 Ready");
+	loop {
+		let mut line = String::new();
+		if reader.read_line(&mut line).unwrap() == 0 {
+			return;
+		}
+		let line = line.trim_end();
+		let (command, argument) = match line.find(' ') {
+			Some(split) => (&line[..split], &line[split + 1..]),
+			None => (line, ""),
+		};
+
+		match command {
+			"AUTH" => {
+				reply(&mut reader, "234 Proceed with negotiation");
+				let conn = ServerConnection::new(config.clone()).unwrap();
+				let stream = StreamOwned::new(conn, raw.try_clone().unwrap());
+				reader = BufReader::new(Box::new(stream));
+			}
+			"PBSZ" | "PROT" | "TYPE" => reply(&mut reader, "200 OK"),
+			"USER" => reply(&mut reader, "331 Password required"),
+			"PASS" => reply(&mut reader, "230 Logged in"),
+			"PWD" => reply(&mut reader, "257 \"/\" is the current directory"),
+			"PASV" => {
+				let data_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+				let port = data_listener.local_addr().unwrap().port();
+				passive = Some(data_listener);
+				reply(
+					&mut reader,
+					&format!("227 Entering Passive Mode (127,0,0,1,{},{})", port / 256, port % 256),
+				);
+			}
+			"RETR" if argument == "hello.txt" => {
+				reply(&mut reader, "150 Opening data connection");
+				let (data, _) = passive.take().unwrap().accept().unwrap();
+				let conn = ServerConnection::new(config.clone()).unwrap();
+				let mut data = StreamOwned::new(conn, data);
+				data.write_all(b"Hello").unwrap();
+				data.conn.send_close_notify();
+				data.flush().unwrap();
+				drop(data);
+				reply(&mut reader, "226 Transfer complete");
+			}
+			// The data connection is left waiting, without ever getting a handshake
+			"RETR" => reply(&mut reader, "550 No such file"),
+			"QUIT" => {
+				reply(&mut reader, "221 Goodbye");
+				return;
+			}
+			_ => reply(&mut reader, "502 Command not implemented"),
+		}
+	}
+}
+
+fn reply(reader: &mut BufReader<Box<dyn Stream>>, line: &str) {
+	let stream = reader.get_mut();
+	stream.write_all(format!("{}\r\n", line).as_bytes()).unwrap();
+	stream.flush().unwrap();
+}
+
+/// Starts a server and connects to it, returning the settings to secure the connection with
+fn connect() -> (FtpConnection, TlsConfig, thread::JoinHandle<()>) {
+	let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+	let certificate: CertificateDer<'static> = certified.cert.der().clone();
+	let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+
+	let server_config = ServerConfig::builder()
+		.with_no_client_auth()
+		.with_single_cert(vec![certificate.clone()], key)
+		.unwrap();
+
+	let mut roots = RootCertStore::empty();
+	roots.add(certificate).unwrap();
+	let client_config = ClientConfig::builder()
+		.with_root_certificates(roots)
+		.with_no_client_auth();
+	let tls_config = TlsConfig::new(Arc::new(client_config), "localhost").unwrap();
+
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+	let server = thread::spawn(move || serve(listener, Arc::new(server_config)));
+
+	(common::connect(addr), tls_config, server)
+}
+
+#[test]
+fn transfers_over_explicit_tls() {
+	let (mut ftp_conn, tls_config, server) = connect();
+
+	ftp_conn.secure(tls_config).unwrap();
+	ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	assert_eq!(ftp_conn.fetch_file("hello.txt").unwrap(), b"Hello");
+
+	ftp_conn.quit().unwrap();
+	server.join().unwrap();
+}
+
+#[test]
+fn securing_twice_is_an_error() {
+	let (mut ftp_conn, tls_config, server) = connect();
+
+	ftp_conn.secure(tls_config.clone()).unwrap();
+	assert!(matches!(ftp_conn.secure(tls_config), Err(FtpError::TlsError(_))));
+
+	ftp_conn.quit().unwrap();
+	server.join().unwrap();
+}
+
+#[test]
+fn rejected_transfer_keeps_replies_in_step() {
+	let (mut ftp_conn, tls_config, server) = connect();
+
+	ftp_conn.secure(tls_config).unwrap();
+	ftp_conn.login("anonymous", Some("fake@email.service")).unwrap();
+	assert!(matches!(
+		ftp_conn.fetch_file("missing.txt"),
+		Err(FtpError::FileUnavailable)
+	));
+	assert_eq!(ftp_conn.pwd().unwrap(), "/");
+
+	ftp_conn.quit().unwrap();
+	server.join().unwrap();
+}
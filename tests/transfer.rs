@@ -0,0 +1,137 @@
+//! Failed and aborted transfers against an in-process server.
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+
+use common::Reply;
+use ftp_lib::error::FtpError;
+use ftp_lib::{DataMode, FtpTimeouts};
+
+/// Checks the replies to a transfer whose data connection could not be opened are all read
+fn refused_data_connection(transfer_replies: &'static [&'static str]) {
+	// Nothing listens on the port the server announces
+	let port = TcpListener::bind("127.0.0.1:0")
+		.unwrap()
+		.local_addr()
+		.unwrap()
+		.port();
+	let pasv = format!("227 Entering Passive Mode (127,0,0,1,{},{})", port / 256, port % 256);
+	let (mut ftp_conn, _commands, server) = common::start(move |command| match command {
+		"PASV" => vec![Reply::Line(pasv.clone())],
+		"RETR hello.txt" => transfer_replies.iter().map(|&line| line.into()).collect(),
+		_ => common::reply(command),
+	});
+
+	assert!(matches!(
+		ftp_conn.fetch_file("hello.txt"),
+		Err(FtpError::DatastreamConnectionError)
+	));
+	assert_eq!(ftp_conn.pwd().unwrap(), "/");
+
+	ftp_conn.quit().unwrap();
+	server.join().unwrap();
+}
+
+#[test]
+fn refused_data_connection_reads_the_error_reply() {
+	refused_data_connection(&["425 Can't open data connection"]);
+}
+
+#[test]
+fn refused_data_connection_reads_the_start_and_error_replies() {
+	refused_data_connection(&["150 Opening data connection", "425 Can't open data connection"]);
+}
+
+#[test]
+fn data_connection_never_made_reads_the_error_reply() {
+	let (mut ftp_conn, _commands, server) = common::start(|command| match command {
+		_ if command.starts_with("PORT ") => vec!["200 OK".into()],
+		// The server gives up on connecting to the client straight away
+		"RETR hello.txt" => vec![
+			"150 Opening data connection".into(),
+			"425 Can't open data connection".into(),
+		],
+		_ => common::reply(command),
+	});
+	ftp_conn.set_data_mode(DataMode::Active);
+	let timeouts = FtpTimeouts {
+		connect: Some(Duration::from_millis(200)),
+		..ftp_conn.timeouts()
+	};
+	ftp_conn.set_timeouts(timeouts).unwrap();
+
+	assert!(matches!(ftp_conn.fetch_file("hello.txt"), Err(FtpError::Timeout)));
+	assert_eq!(ftp_conn.pwd().unwrap(), "/");
+
+	ftp_conn.quit().unwrap();
+	server.join().unwrap();
+}
+
+/// Checks the connection is still usable after aborting a download the server answers with `abor_replies`
+fn aborted_download(abor_replies: &'static [&'static str]) {
+	let (mut ftp_conn, commands, server) = common::start(move |command| match command {
+		"PASV" => vec![Reply::Passive],
+		"RETR big.bin" => vec!["150 Opening data connection".into(), Reply::Data(b"partial")],
+		"ABOR" => std::iter::once(Reply::CloseData)
+			.chain(abor_replies.iter().map(|&line| line.into()))
+			.collect(),
+		_ => common::reply(command),
+	});
+
+	let mut reader = ftp_conn.retr_reader("big.bin").unwrap();
+	let mut partial = [0; 7];
+	reader.read_exact(&mut partial).unwrap();
+	reader.abort().unwrap();
+	assert_eq!(ftp_conn.pwd().unwrap(), "/");
+
+	ftp_conn.quit().unwrap();
+	server.join().unwrap();
+	assert_eq!(
+		commands.try_iter().collect::<Vec<_>>(),
+		["PASV", "RETR big.bin", "ABOR", "PWD", "QUIT"]
+	);
+}
+
+#[test]
+fn abort_reads_the_interrupted_transfer_and_abor_replies() {
+	aborted_download(&["426 Transfer aborted", "226 ABOR successful"]);
+}
+
+#[test]
+fn abort_reads_the_finished_transfer_and_abor_replies() {
+	aborted_download(&["226 Transfer complete", "226 ABOR successful"]);
+}
+
+#[test]
+fn abort_reads_the_lone_abor_reply() {
+	aborted_download(&["225 No transfer to abort"]);
+}
+
+#[test]
+fn dropped_writer_aborts_the_upload() {
+	let (mut ftp_conn, commands, server) = common::start(|command| match command {
+		"PASV" => vec![Reply::Passive],
+		"STOR big.bin" => vec!["150 Opening data connection".into(), Reply::Data(b"")],
+		"ABOR" => vec![
+			Reply::CloseData,
+			"426 Transfer aborted".into(),
+			"226 ABOR successful".into(),
+		],
+		_ => common::reply(command),
+	});
+
+	let mut writer = ftp_conn.stor_writer("big.bin").unwrap();
+	writer.write_all(b"partial").unwrap();
+	drop(writer);
+	assert_eq!(ftp_conn.pwd().unwrap(), "/");
+
+	ftp_conn.quit().unwrap();
+	server.join().unwrap();
+	assert_eq!(
+		commands.try_iter().collect::<Vec<_>>(),
+		["PASV", "STOR big.bin", "ABOR", "PWD", "QUIT"]
+	);
+}